@@ -1,9 +1,8 @@
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, Rgba};
-use nalgebra::Vector2;
+use image::{DynamicImage, Rgba};
 use std::path::Path;
-use imageproc::{drawing::{draw_cross_mut, Canvas}, point::Point};
+use imageproc::drawing::draw_cross_mut;
 
-use vslam_core::keyframe::Feature;
+use vslam_core::feature::Detector;
 use vslam_frontend::sift::SIFT;
 
 fn main() {
@@ -12,14 +11,14 @@ fn main() {
     //let image2 = image::open(Path::new("C:/Users/Administrator/Documents/vslam/data/V1_01_easy/mav0/cam0/data/1403715413312143104.png")).unwrap();
 
     //
-    let features = SIFT::extract_features(&image);
+    let keypoints = SIFT::default().detect(&image.to_luma8());
 
     // 创建一个可变图像以绘制特征点
     let mut output_image = DynamicImage::ImageRgb8(image.to_rgb8());
 
     // 在图像上绘制特征点
-    for feature in features.iter() {
-        let point=feature.location;
+    for keypoint in keypoints.iter() {
+        let point=keypoint.location;
         let color:Rgba<u8> = Rgba([0, 255, 0,255]);
         draw_cross_mut(&mut output_image, color, point.x as i32,point.y as i32);
     }
@@ -0,0 +1,514 @@
+use crate::lie::hat;
+use nalgebra::{Matrix2, Matrix2x3, Matrix2x6, Matrix3, Matrix3x6, Vector2, Vector3};
+
+/// 去畸变迭代次数上限
+const UNDISTORT_MAX_ITERATIONS: usize = 20;
+/// 去畸变收敛阈值（归一化平面）
+const UNDISTORT_EPSILON: f64 = 1e-12;
+
+/// 相机模型的公共接口
+/// 前端与后端通过该trait使用任意相机模型
+pub trait Camera {
+    /// 相机坐标系下的三维点投影到像素，无法投影时返回None
+    fn project(&self, point: &Vector3<f64>) -> Option<Vector2<f64>>;
+
+    /// 像素反投影为单位方位向量
+    fn unproject(&self, pixel: &Vector2<f64>) -> Vector3<f64>;
+
+    /// 投影对相机坐标系下三维点的雅可比 ∂uv/∂Pc (2x3)
+    /// 仅在project返回Some的点上有意义
+    fn project_jacobian(&self, point: &Vector3<f64>) -> Matrix2x3<f64>;
+
+    /// 投影对相机位姿的雅可比 ∂uv/∂δ (2x6)
+    /// point为相机坐标系下的点Pc = Tcw * Pw
+    /// 扰动为Tcw的左乘扰动 exp(δ^) * Tcw，δ = [ρ; φ]，平移在前旋转在后
+    /// 此时 ∂Pc/∂δ = [I, -Pc^]
+    fn pose_jacobian(&self, point: &Vector3<f64>) -> Matrix2x6<f64> {
+        let mut point_jacobian = Matrix3x6::zeros();
+        point_jacobian
+            .fixed_view_mut::<3, 3>(0, 0)
+            .copy_from(&Matrix3::identity());
+        point_jacobian
+            .fixed_view_mut::<3, 3>(0, 3)
+            .copy_from(&-hat(point));
+        self.project_jacobian(point) * point_jacobian
+    }
+
+    /// 内参矩阵K
+    fn intrinsic_matrix(&self) -> Matrix3<f64>;
+
+    /// 图像宽
+    fn width(&self) -> u32;
+
+    /// 图像高
+    fn height(&self) -> u32;
+
+    /// 像素去畸变，返回同一内参K下理想针孔模型的像素坐标
+    /// 可直接用于ORB::location、SIFT::location等特征点位置
+    fn undistort_point(&self, pixel: &Vector2<f64>) -> Vector2<f64> {
+        let bearing = self.unproject(pixel);
+        let k = self.intrinsic_matrix();
+        Vector2::new(
+            k[(0, 0)] * bearing.x / bearing.z + k[(0, 2)],
+            k[(1, 1)] * bearing.y / bearing.z + k[(1, 2)],
+        )
+    }
+
+    /// 批量去畸变
+    fn undistort_points(&self, pixels: &[Vector2<f64>]) -> Vec<Vector2<f64>> {
+        pixels
+            .iter()
+            .map(|pixel| self.undistort_point(pixel))
+            .collect()
+    }
+
+    /// 判断像素是否在图像内
+    fn is_in_image(&self, pixel: &Vector2<f64>) -> bool {
+        pixel.x >= 0.0
+            && pixel.y >= 0.0
+            && pixel.x < self.width() as f64
+            && pixel.y < self.height() as f64
+    }
+}
+
+/// 针孔相机模型
+/// 内参 fx, fy, cx, cy
+/// 径向-切向(radtan)畸变 k1, k2, p1, p2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PinholeCamera {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub k1: f64,
+    pub k2: f64,
+    pub p1: f64,
+    pub p2: f64,
+    pub width: u32,  // 图像宽
+    pub height: u32, // 图像高
+}
+
+impl PinholeCamera {
+    /// 无畸变的针孔相机
+    pub fn new(fx: f64, fy: f64, cx: f64, cy: f64, width: u32, height: u32) -> Self {
+        PinholeCamera {
+            fx,
+            fy,
+            cx,
+            cy,
+            k1: 0.0,
+            k2: 0.0,
+            p1: 0.0,
+            p2: 0.0,
+            width,
+            height,
+        }
+    }
+
+    /// 设置radtan畸变参数
+    pub fn with_distortion(mut self, k1: f64, k2: f64, p1: f64, p2: f64) -> Self {
+        self.k1 = k1;
+        self.k2 = k2;
+        self.p1 = p1;
+        self.p2 = p2;
+        self
+    }
+
+    /// 是否带畸变
+    pub fn is_distorted(&self) -> bool {
+        self.k1 != 0.0 || self.k2 != 0.0 || self.p1 != 0.0 || self.p2 != 0.0
+    }
+
+    fn pixel_to_normalized(&self, pixel: &Vector2<f64>) -> Vector2<f64> {
+        Vector2::new((pixel.x - self.cx) / self.fx, (pixel.y - self.cy) / self.fy)
+    }
+
+    fn normalized_to_pixel(&self, normalized: &Vector2<f64>) -> Vector2<f64> {
+        Vector2::new(
+            self.fx * normalized.x + self.cx,
+            self.fy * normalized.y + self.cy,
+        )
+    }
+
+    /// 对归一化平面坐标加畸变，同时返回畸变函数的2x2雅可比
+    fn distort(&self, normalized: &Vector2<f64>) -> (Vector2<f64>, Matrix2<f64>) {
+        let (x, y) = (normalized.x, normalized.y);
+        let r2 = x * x + y * y;
+        let radial = 1.0 + self.k1 * r2 + self.k2 * r2 * r2;
+        let d_radial = self.k1 + 2.0 * self.k2 * r2; // d(radial)/d(r2)
+
+        let distorted = Vector2::new(
+            x * radial + 2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+            y * radial + self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+        );
+
+        let dxd_dx = radial + 2.0 * x * x * d_radial + 2.0 * self.p1 * y + 6.0 * self.p2 * x;
+        let dxd_dy = 2.0 * x * y * d_radial + 2.0 * self.p1 * x + 2.0 * self.p2 * y;
+        let dyd_dy = radial + 2.0 * y * y * d_radial + 6.0 * self.p1 * y + 2.0 * self.p2 * x;
+        let jacobian = Matrix2::new(dxd_dx, dxd_dy, dxd_dy, dyd_dy);
+
+        (distorted, jacobian)
+    }
+
+    /// 归一化平面去畸变，高斯牛顿迭代求解distort的逆
+    fn undistort_normalized(&self, distorted: &Vector2<f64>) -> Vector2<f64> {
+        if !self.is_distorted() {
+            return *distorted;
+        }
+
+        let mut normalized = *distorted;
+        for _ in 0..UNDISTORT_MAX_ITERATIONS {
+            let (estimate, jacobian) = self.distort(&normalized);
+            let residual = estimate - distorted;
+            let step = match jacobian.try_inverse() {
+                Some(inverse) => inverse * residual,
+                None => break,
+            };
+            normalized -= step;
+            if step.norm_squared() < UNDISTORT_EPSILON {
+                break;
+            }
+        }
+        normalized
+    }
+}
+
+impl Camera for PinholeCamera {
+    fn project(&self, point: &Vector3<f64>) -> Option<Vector2<f64>> {
+        if point.z <= 0.0 {
+            return None;
+        }
+        let normalized = Vector2::new(point.x / point.z, point.y / point.z);
+        let (distorted, _) = self.distort(&normalized);
+        Some(self.normalized_to_pixel(&distorted))
+    }
+
+    fn unproject(&self, pixel: &Vector2<f64>) -> Vector3<f64> {
+        let normalized = self.undistort_normalized(&self.pixel_to_normalized(pixel));
+        Vector3::new(normalized.x, normalized.y, 1.0).normalize()
+    }
+
+    fn project_jacobian(&self, point: &Vector3<f64>) -> Matrix2x3<f64> {
+        let inv_z = 1.0 / point.z;
+        let normalized = Vector2::new(point.x * inv_z, point.y * inv_z);
+        let (_, distortion_jacobian) = self.distort(&normalized);
+        // ∂(x/z, y/z)/∂Pc
+        let normalized_jacobian = Matrix2x3::new(
+            inv_z,
+            0.0,
+            -normalized.x * inv_z,
+            0.0,
+            inv_z,
+            -normalized.y * inv_z,
+        );
+        Matrix2::new(self.fx, 0.0, 0.0, self.fy) * distortion_jacobian * normalized_jacobian
+    }
+
+    fn intrinsic_matrix(&self) -> Matrix3<f64> {
+        Matrix3::new(self.fx, 0.0, self.cx, 0.0, self.fy, self.cy, 0.0, 0.0, 1.0)
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn undistort_point(&self, pixel: &Vector2<f64>) -> Vector2<f64> {
+        let normalized = self.undistort_normalized(&self.pixel_to_normalized(pixel));
+        self.normalized_to_pixel(&normalized)
+    }
+}
+
+/// Kannala-Brandt等距(equidistant)鱼眼相机模型
+/// θd = θ(1 + k1θ² + k2θ⁴ + k3θ⁶ + k4θ⁸)，θ为入射角
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KannalaBrandtCamera {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub k1: f64,
+    pub k2: f64,
+    pub k3: f64,
+    pub k4: f64,
+    pub width: u32,  // 图像宽
+    pub height: u32, // 图像高
+}
+
+impl KannalaBrandtCamera {
+    /// 无畸变的等距相机
+    pub fn new(fx: f64, fy: f64, cx: f64, cy: f64, width: u32, height: u32) -> Self {
+        KannalaBrandtCamera {
+            fx,
+            fy,
+            cx,
+            cy,
+            k1: 0.0,
+            k2: 0.0,
+            k3: 0.0,
+            k4: 0.0,
+            width,
+            height,
+        }
+    }
+
+    /// 设置等距畸变参数
+    pub fn with_distortion(mut self, k1: f64, k2: f64, k3: f64, k4: f64) -> Self {
+        self.k1 = k1;
+        self.k2 = k2;
+        self.k3 = k3;
+        self.k4 = k4;
+        self
+    }
+
+    /// 入射角θ映射到畸变角θd，同时返回dθd/dθ
+    fn distort_theta(&self, theta: f64) -> (f64, f64) {
+        let theta2 = theta * theta;
+        let theta4 = theta2 * theta2;
+        let theta6 = theta4 * theta2;
+        let theta8 = theta4 * theta4;
+        let theta_d = theta
+            * (1.0 + self.k1 * theta2 + self.k2 * theta4 + self.k3 * theta6 + self.k4 * theta8);
+        let derivative = 1.0
+            + 3.0 * self.k1 * theta2
+            + 5.0 * self.k2 * theta4
+            + 7.0 * self.k3 * theta6
+            + 9.0 * self.k4 * theta8;
+        (theta_d, derivative)
+    }
+
+    /// 牛顿迭代由θd求θ
+    fn undistort_theta(&self, theta_d: f64) -> f64 {
+        let mut theta = theta_d;
+        for _ in 0..UNDISTORT_MAX_ITERATIONS {
+            let (estimate, derivative) = self.distort_theta(theta);
+            if derivative.abs() < f64::EPSILON {
+                break;
+            }
+            let step = (estimate - theta_d) / derivative;
+            theta -= step;
+            if step * step < UNDISTORT_EPSILON {
+                break;
+            }
+        }
+        theta
+    }
+}
+
+impl Camera for KannalaBrandtCamera {
+    /// 仅投影相机前方的点
+    fn project(&self, point: &Vector3<f64>) -> Option<Vector2<f64>> {
+        if point.z <= 0.0 {
+            return None;
+        }
+        let r = (point.x * point.x + point.y * point.y).sqrt();
+        if r < f64::EPSILON {
+            return Some(Vector2::new(self.cx, self.cy));
+        }
+        let theta = r.atan2(point.z);
+        let (theta_d, _) = self.distort_theta(theta);
+        Some(Vector2::new(
+            self.fx * theta_d * point.x / r + self.cx,
+            self.fy * theta_d * point.y / r + self.cy,
+        ))
+    }
+
+    fn unproject(&self, pixel: &Vector2<f64>) -> Vector3<f64> {
+        let mx = (pixel.x - self.cx) / self.fx;
+        let my = (pixel.y - self.cy) / self.fy;
+        let theta_d = (mx * mx + my * my).sqrt();
+        if theta_d < f64::EPSILON {
+            return Vector3::new(0.0, 0.0, 1.0);
+        }
+        let theta = self.undistort_theta(theta_d);
+        let scale = theta.sin() / theta_d;
+        Vector3::new(mx * scale, my * scale, theta.cos())
+    }
+
+    fn project_jacobian(&self, point: &Vector3<f64>) -> Matrix2x3<f64> {
+        let (x, y, z) = (point.x, point.y, point.z);
+        let r2 = x * x + y * y;
+        let r = r2.sqrt();
+        if r < 1e-8 {
+            // 光轴附近退化为针孔模型
+            return Matrix2x3::new(
+                self.fx / z,
+                0.0,
+                -self.fx * x / (z * z),
+                0.0,
+                self.fy / z,
+                -self.fy * y / (z * z),
+            );
+        }
+
+        let theta = r.atan2(z);
+        let (theta_d, d_theta_d) = self.distort_theta(theta);
+        let rho2 = r2 + z * z;
+        // θ对x, y, z的偏导
+        let dtheta = Vector3::new(z * x / (r * rho2), z * y / (r * rho2), -r / rho2);
+        // ψ = θd / r 对x, y, z的偏导
+        let psi = theta_d / r;
+        let dr = Vector3::new(x / r, y / r, 0.0);
+        let dpsi = dtheta * (d_theta_d / r) - dr * (theta_d / r2);
+
+        Matrix2x3::new(
+            self.fx * (psi + x * dpsi.x),
+            self.fx * x * dpsi.y,
+            self.fx * x * dpsi.z,
+            self.fy * y * dpsi.x,
+            self.fy * (psi + y * dpsi.y),
+            self.fy * y * dpsi.z,
+        )
+    }
+
+    fn intrinsic_matrix(&self) -> Matrix3<f64> {
+        Matrix3::new(self.fx, 0.0, self.cx, 0.0, self.fy, self.cy, 0.0, 0.0, 1.0)
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn euroc_cam0() -> PinholeCamera {
+        PinholeCamera::new(458.654, 457.296, 367.215, 248.375, 752, 480).with_distortion(
+            -0.28340811,
+            0.07395907,
+            0.00019359,
+            1.76187114e-05,
+        )
+    }
+
+    #[test]
+    fn project_unproject_round_trip() {
+        let camera = euroc_cam0();
+        let point = Vector3::new(0.4, -0.3, 2.0);
+        let pixel = camera.project(&point).unwrap();
+        let bearing = camera.unproject(&pixel);
+        assert!((bearing - point.normalize()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn undistort_matches_ideal_projection() {
+        let camera = euroc_cam0();
+        let ideal = PinholeCamera::new(camera.fx, camera.fy, camera.cx, camera.cy, 752, 480);
+        let point = Vector3::new(-0.5, 0.2, 1.5);
+        let distorted = camera.project(&point).unwrap();
+        let undistorted = camera.undistort_point(&distorted);
+        assert!((undistorted - ideal.project(&point).unwrap()).norm() < 1e-6);
+    }
+
+    #[test]
+    fn kannala_brandt_round_trip() {
+        let camera = kannala_brandt();
+        for point in [
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.3, -0.2, 1.0),
+            Vector3::new(-2.0, 1.5, 0.5),
+        ] {
+            let pixel = camera.project(&point).unwrap();
+            let bearing = camera.unproject(&pixel);
+            assert!((bearing - point.normalize()).norm() < 1e-9);
+        }
+    }
+
+    fn kannala_brandt() -> KannalaBrandtCamera {
+        KannalaBrandtCamera::new(190.978, 190.973, 254.932, 256.897, 512, 512)
+            .with_distortion(0.003482, 0.000715, -0.002053, 0.000203)
+    }
+
+    /// 中心差分计算投影对三维点的数值雅可比
+    fn numeric_project_jacobian<C: Camera>(camera: &C, point: &Vector3<f64>) -> Matrix2x3<f64> {
+        let h = 1e-6;
+        let mut jacobian = Matrix2x3::zeros();
+        for i in 0..3 {
+            let mut delta = Vector3::zeros();
+            delta[i] = h;
+            let plus = camera.project(&(point + delta)).unwrap();
+            let minus = camera.project(&(point - delta)).unwrap();
+            jacobian.set_column(i, &((plus - minus) / (2.0 * h)));
+        }
+        jacobian
+    }
+
+    /// 中心差分计算投影对左乘位姿扰动的数值雅可比
+    fn numeric_pose_jacobian<C: Camera>(camera: &C, point: &Vector3<f64>) -> Matrix2x6<f64> {
+        let h = 1e-6;
+        let perturb = |i: usize, step: f64| {
+            let mut delta = Vector3::zeros();
+            delta[i % 3] = step;
+            let moved = if i < 3 {
+                point + delta
+            } else {
+                nalgebra::Rotation3::new(delta) * point
+            };
+            camera.project(&moved).unwrap()
+        };
+        let mut jacobian = Matrix2x6::zeros();
+        for i in 0..6 {
+            jacobian.set_column(i, &((perturb(i, h) - perturb(i, -h)) / (2.0 * h)));
+        }
+        jacobian
+    }
+
+    fn check_jacobians<C: Camera>(camera: &C, points: &[Vector3<f64>]) {
+        for point in points {
+            let analytic = camera.project_jacobian(point);
+            let numeric = numeric_project_jacobian(camera, point);
+            assert!(
+                (analytic - numeric).norm() < 1e-4 * analytic.norm().max(1.0),
+                "point jacobian mismatch at {point}: {analytic} vs {numeric}"
+            );
+
+            let analytic = camera.pose_jacobian(point);
+            let numeric = numeric_pose_jacobian(camera, point);
+            assert!(
+                (analytic - numeric).norm() < 1e-4 * analytic.norm().max(1.0),
+                "pose jacobian mismatch at {point}: {analytic} vs {numeric}"
+            );
+        }
+    }
+
+    #[test]
+    fn pinhole_jacobians_match_finite_differences() {
+        let points = [
+            Vector3::new(0.4, -0.3, 2.0),
+            Vector3::new(-0.8, 0.5, 1.2),
+            Vector3::new(0.0, 0.0, 3.0),
+        ];
+        check_jacobians(&euroc_cam0(), &points);
+        check_jacobians(
+            &PinholeCamera::new(400.0, 410.0, 320.0, 240.0, 640, 480),
+            &points,
+        );
+    }
+
+    #[test]
+    fn kannala_brandt_jacobians_match_finite_differences() {
+        let points = [
+            Vector3::new(0.3, -0.2, 1.0),
+            Vector3::new(-2.0, 1.5, 0.5),
+            Vector3::new(0.0, 0.0, 2.0),
+            Vector3::new(1e-9, -1e-9, 2.0),
+        ];
+        check_jacobians(&kannala_brandt(), &points);
+    }
+
+    #[test]
+    fn point_behind_camera_is_not_projected() {
+        assert!(euroc_cam0()
+            .project(&Vector3::new(0.0, 0.0, -1.0))
+            .is_none());
+    }
+}
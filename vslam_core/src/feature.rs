@@ -0,0 +1,102 @@
+use image::GrayImage;
+use nalgebra::Vector2;
+
+/// 特征点
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyPoint {
+    pub location: Vector2<f64>, // 原图像素坐标
+    pub size: f64,              // 特征邻域直径（原图像素）
+    pub angle: f64,             // 主方向，弧度
+    pub response: f64,          // 响应强度，越大越好
+    pub octave: usize,          // 所在金字塔层
+}
+
+impl KeyPoint {
+    /// 只有位置的特征点，其余属性为0
+    pub fn new(location: Vector2<f64>) -> Self {
+        KeyPoint {
+            location,
+            size: 0.0,
+            angle: 0.0,
+            response: 0.0,
+            octave: 0,
+        }
+    }
+}
+
+/// 描述子，定义描述子之间的距离
+pub trait Descriptor: Clone {
+    fn distance(&self, other: &Self) -> f64;
+}
+
+/// 二进制描述子，汉明距离
+impl<const N: usize> Descriptor for [u64; N] {
+    fn distance(&self, other: &Self) -> f64 {
+        self.iter()
+            .zip(other.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum::<u32>() as f64
+    }
+}
+
+/// 浮点描述子，欧氏距离
+impl<const N: usize> Descriptor for [f32; N] {
+    fn distance(&self, other: &Self) -> f64 {
+        self.iter()
+            .zip(other.iter())
+            .map(|(a, b)| ((a - b) * (a - b)) as f64)
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// 从一组描述子中选出最有代表性的一个
+/// 即到其余描述子距离的中值最小者
+pub fn distinctive_descriptor<D: Descriptor>(descriptors: &[D]) -> Option<D> {
+    let n = descriptors.len();
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let mut distances: Vec<f64> = (0..n)
+            .filter(|&j| j != i)
+            .map(|j| descriptors[i].distance(&descriptors[j]))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        let median = distances.get(distances.len() / 2).copied().unwrap_or(0.0);
+        if best.is_none_or(|(best_median, _)| median < best_median) {
+            best = Some((median, i));
+        }
+    }
+    best.map(|(_, i)| descriptors[i].clone())
+}
+
+/// 特征点检测器
+pub trait Detector {
+    /// 检测特征点
+    fn detect(&self, image: &GrayImage) -> Vec<KeyPoint>;
+}
+
+/// 描述子提取器
+pub trait DescriptorExtractor {
+    type Descriptor: Descriptor;
+
+    /// 为特征点计算描述子，返回的描述子与keypoints一一对应
+    /// 无法计算描述子的特征点会从keypoints中移除
+    fn compute(&self, image: &GrayImage, keypoints: &mut Vec<KeyPoint>) -> Vec<Self::Descriptor>;
+
+    /// 用给定的检测器检测特征点并计算描述子
+    fn detect_and_compute<T: Detector + ?Sized>(
+        &self,
+        detector: &T,
+        image: &GrayImage,
+    ) -> (Vec<KeyPoint>, Vec<Self::Descriptor>) {
+        let mut keypoints = detector.detect(image);
+        let descriptors = self.compute(image, &mut keypoints);
+        (keypoints, descriptors)
+    }
+}
+
+/// 描述子匹配器
+pub trait Matcher<D> {
+    /// 匹配两组描述子，返回(query索引, train索引)
+    fn match_descriptors(&self, query: &[D], train: &[D]) -> Vec<(usize, usize)>;
+}
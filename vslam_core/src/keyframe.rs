@@ -1,15 +1,125 @@
-use image::DynamicImage;
-pub struct KeyFrame {
-    id: usize,                       // 编号
-    image_path: String,              // 文件地址
-    features: Vec<Box<dyn Feature>>, // 特征
+use crate::feature::KeyPoint;
+use crate::lie::SE3;
+use nalgebra::Vector3;
+
+/// 关键帧，D为描述子类型
+pub struct KeyFrame<D> {
+    id: usize,                      // 编号
+    timestamp: f64,                 // 时间戳，单位秒
+    image_path: String,             // 文件地址
+    pose: SE3,                      // 位姿Twc，相机到世界
+    keypoints: Vec<KeyPoint>,       // 特征点
+    descriptors: Vec<D>,            // 描述子，与keypoints一一对应
+    map_points: Vec<Option<usize>>, // 特征对应的地图点id
+}
+
+impl<D> KeyFrame<D> {
+    /// keypoints与descriptors长度不同时返回None
+    pub fn new(
+        id: usize,
+        timestamp: f64,
+        image_path: String,
+        pose: SE3,
+        keypoints: Vec<KeyPoint>,
+        descriptors: Vec<D>,
+    ) -> Option<Self> {
+        if keypoints.len() != descriptors.len() {
+            return None;
+        }
+        let map_points = vec![None; keypoints.len()];
+        Some(KeyFrame {
+            id,
+            timestamp,
+            image_path,
+            pose,
+            keypoints,
+            descriptors,
+            map_points,
+        })
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+
+    pub fn image_path(&self) -> &str {
+        &self.image_path
+    }
+
+    /// 位姿Twc，相机坐标系到世界坐标系
+    pub fn pose(&self) -> &SE3 {
+        &self.pose
+    }
+
+    pub fn set_pose(&mut self, pose: SE3) {
+        self.pose = pose;
+    }
+
+    /// 相机光心在世界坐标系下的位置
+    pub fn camera_center(&self) -> Vector3<f64> {
+        self.pose.translation()
+    }
+
+    /// 特征数量
+    pub fn num_features(&self) -> usize {
+        self.keypoints.len()
+    }
+
+    pub fn keypoints(&self) -> &[KeyPoint] {
+        &self.keypoints
+    }
+
+    pub fn keypoint(&self, index: usize) -> Option<&KeyPoint> {
+        self.keypoints.get(index)
+    }
+
+    pub fn descriptors(&self) -> &[D] {
+        &self.descriptors
+    }
+
+    pub fn descriptor(&self, index: usize) -> Option<&D> {
+        self.descriptors.get(index)
+    }
+
+    /// 第index个特征对应的地图点id
+    pub fn map_point(&self, index: usize) -> Option<usize> {
+        self.map_points.get(index).copied().flatten()
+    }
+
+    pub fn map_points(&self) -> &[Option<usize>] {
+        &self.map_points
+    }
+
+    /// 只能由Map修改，保证双向观测关系一致
+    pub(crate) fn set_map_point(&mut self, index: usize, map_point: Option<usize>) {
+        self.map_points[index] = map_point;
+    }
 }
 
-pub trait Feature {
-    fn extract_features(image: &DynamicImage) -> Vec<Self>
-    where
-        Self: Sized;
-    fn match_features(feature1: &Vec<Self>, feature2: &Vec<Self>) -> Vec<(usize, usize)>
-    where
-        Self: Sized;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn new_rejects_mismatched_descriptors() {
+        let keypoints = vec![KeyPoint::new(Vector2::zeros()); 3];
+        let keyframe = |descriptors: Vec<u8>| {
+            KeyFrame::new(
+                0,
+                0.0,
+                String::new(),
+                SE3::identity(),
+                keypoints.clone(),
+                descriptors,
+            )
+        };
+        assert!(keyframe(vec![0; 2]).is_none());
+        let keyframe = keyframe(vec![0; 3]).unwrap();
+        assert_eq!(keyframe.map_points(), &[None; 3]);
+    }
 }
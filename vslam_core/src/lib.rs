@@ -1 +1,6 @@
+pub mod camera;
+pub mod feature;
 pub mod keyframe;
+pub mod lie;
+pub mod map;
+pub mod map_point;
\ No newline at end of file
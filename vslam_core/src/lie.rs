@@ -0,0 +1,232 @@
+use nalgebra::{
+    Isometry3, Matrix3, Matrix4, Matrix6, Point3, Translation3, UnitQuaternion, Vector3, Vector6,
+};
+use std::ops::Mul;
+
+/// 小角度阈值，低于该值时使用泰勒展开
+const SMALL_ANGLE: f64 = 1e-8;
+
+/// 向量的反对称矩阵 v^
+pub fn hat(v: &Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::new(0.0, -v.z, v.y, v.z, 0.0, -v.x, -v.y, v.x, 0.0)
+}
+
+/// 反对称矩阵还原为向量
+pub fn vee(m: &Matrix3<f64>) -> Vector3<f64> {
+    Vector3::new(m[(2, 1)], m[(0, 2)], m[(1, 0)])
+}
+
+/// SO(3)指数映射，旋转向量φ转为旋转
+pub fn so3_exp(phi: &Vector3<f64>) -> UnitQuaternion<f64> {
+    UnitQuaternion::from_scaled_axis(*phi)
+}
+
+/// SO(3)对数映射，旋转转为旋转向量φ
+pub fn so3_log(rotation: &UnitQuaternion<f64>) -> Vector3<f64> {
+    rotation.scaled_axis()
+}
+
+/// SO(3)左雅可比 Jl(φ)
+pub fn so3_left_jacobian(phi: &Vector3<f64>) -> Matrix3<f64> {
+    let theta = phi.norm();
+    let phi_hat = hat(phi);
+    if theta < SMALL_ANGLE {
+        return Matrix3::identity() + 0.5 * phi_hat;
+    }
+    let theta2 = theta * theta;
+    Matrix3::identity()
+        + (1.0 - theta.cos()) / theta2 * phi_hat
+        + (theta - theta.sin()) / (theta2 * theta) * phi_hat * phi_hat
+}
+
+/// SO(3)左雅可比的逆 Jl(φ)^-1
+pub fn so3_left_jacobian_inverse(phi: &Vector3<f64>) -> Matrix3<f64> {
+    let theta = phi.norm();
+    let phi_hat = hat(phi);
+    if theta < SMALL_ANGLE {
+        return Matrix3::identity() - 0.5 * phi_hat;
+    }
+    let half = 0.5 * theta;
+    Matrix3::identity() - 0.5 * phi_hat
+        + (1.0 - half / half.tan()) / (theta * theta) * phi_hat * phi_hat
+}
+
+/// SE(3)刚体变换，基于nalgebra的Isometry3
+///
+/// 李代数 ξ = [ρ; φ]，平移在前旋转在后，与Camera::pose_jacobian一致
+/// 左扰动：T' = exp(δ^) * T，右扰动：T' = T * exp(δ^)
+/// 两者关系：exp(δ^) * T = T * exp((Ad(T^-1) * δ)^)
+/// 位姿优化中对Tcw使用左扰动，见retract_left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SE3 {
+    isometry: Isometry3<f64>,
+}
+
+impl Default for SE3 {
+    fn default() -> Self {
+        SE3::identity()
+    }
+}
+
+impl SE3 {
+    /// 单位变换
+    pub fn identity() -> Self {
+        SE3 {
+            isometry: Isometry3::identity(),
+        }
+    }
+
+    /// 由旋转和平移构造
+    pub fn new(rotation: UnitQuaternion<f64>, translation: Vector3<f64>) -> Self {
+        SE3 {
+            isometry: Isometry3::from_parts(Translation3::from(translation), rotation),
+        }
+    }
+
+    pub fn from_isometry(isometry: Isometry3<f64>) -> Self {
+        SE3 { isometry }
+    }
+
+    pub fn isometry(&self) -> &Isometry3<f64> {
+        &self.isometry
+    }
+
+    pub fn rotation(&self) -> UnitQuaternion<f64> {
+        self.isometry.rotation
+    }
+
+    pub fn rotation_matrix(&self) -> Matrix3<f64> {
+        self.isometry.rotation.to_rotation_matrix().into_inner()
+    }
+
+    pub fn translation(&self) -> Vector3<f64> {
+        self.isometry.translation.vector
+    }
+
+    /// 4x4齐次变换矩阵
+    pub fn matrix(&self) -> Matrix4<f64> {
+        self.isometry.to_homogeneous()
+    }
+
+    /// 指数映射 ξ = [ρ; φ] -> T
+    pub fn exp(xi: &Vector6<f64>) -> Self {
+        let rho = xi.fixed_rows::<3>(0).into_owned();
+        let phi = xi.fixed_rows::<3>(3).into_owned();
+        SE3::new(so3_exp(&phi), so3_left_jacobian(&phi) * rho)
+    }
+
+    /// 对数映射 T -> ξ = [ρ; φ]
+    pub fn log(&self) -> Vector6<f64> {
+        let phi = so3_log(&self.isometry.rotation);
+        let rho = so3_left_jacobian_inverse(&phi) * self.translation();
+        let mut xi = Vector6::zeros();
+        xi.fixed_rows_mut::<3>(0).copy_from(&rho);
+        xi.fixed_rows_mut::<3>(3).copy_from(&phi);
+        xi
+    }
+
+    /// 伴随矩阵 Ad(T) = [R, t^R; 0, R]
+    pub fn adjoint(&self) -> Matrix6<f64> {
+        let rotation = self.rotation_matrix();
+        let mut adjoint = Matrix6::zeros();
+        adjoint.fixed_view_mut::<3, 3>(0, 0).copy_from(&rotation);
+        adjoint
+            .fixed_view_mut::<3, 3>(0, 3)
+            .copy_from(&(hat(&self.translation()) * rotation));
+        adjoint.fixed_view_mut::<3, 3>(3, 3).copy_from(&rotation);
+        adjoint
+    }
+
+    pub fn inverse(&self) -> Self {
+        SE3 {
+            isometry: self.isometry.inverse(),
+        }
+    }
+
+    /// 复合变换 self * other
+    pub fn compose(&self, other: &SE3) -> Self {
+        SE3 {
+            isometry: self.isometry * other.isometry,
+        }
+    }
+
+    /// 变换三维点
+    pub fn transform_point(&self, point: &Vector3<f64>) -> Vector3<f64> {
+        (self.isometry * Point3::from(*point)).coords
+    }
+
+    /// 沿测地线插值，t=0时为self，t=1时为other
+    pub fn interpolate(&self, other: &SE3, t: f64) -> Self {
+        let delta = self.inverse().compose(other).log();
+        self.compose(&SE3::exp(&(delta * t)))
+    }
+
+    /// 左扰动更新 exp(δ^) * T
+    pub fn retract_left(&self, delta: &Vector6<f64>) -> Self {
+        SE3::exp(delta).compose(self)
+    }
+
+    /// 右扰动更新 T * exp(δ^)
+    pub fn retract_right(&self, delta: &Vector6<f64>) -> Self {
+        self.compose(&SE3::exp(delta))
+    }
+}
+
+impl Mul for SE3 {
+    type Output = SE3;
+
+    fn mul(self, rhs: SE3) -> SE3 {
+        self.compose(&rhs)
+    }
+}
+
+impl Mul<Vector3<f64>> for SE3 {
+    type Output = Vector3<f64>;
+
+    fn mul(self, rhs: Vector3<f64>) -> Vector3<f64> {
+        self.transform_point(&rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_pose() -> SE3 {
+        SE3::exp(&Vector6::new(0.3, -1.2, 0.5, 0.4, -0.2, 0.9))
+    }
+
+    #[test]
+    fn exp_log_round_trip() {
+        for xi in [
+            Vector6::new(0.3, -1.2, 0.5, 0.4, -0.2, 0.9),
+            Vector6::new(1.0, 2.0, 3.0, 0.0, 0.0, 0.0),
+            Vector6::new(0.0, 0.1, 0.0, 1e-10, 0.0, -1e-10),
+            Vector6::new(-0.5, 0.0, 2.0, 0.0, 3.0, 0.0),
+        ] {
+            assert!((SE3::exp(&xi).log() - xi).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn adjoint_moves_left_perturbation_to_right() {
+        let pose = sample_pose();
+        let delta = Vector6::new(0.01, -0.02, 0.03, 0.02, 0.01, -0.03);
+        let left = pose.retract_left(&delta);
+        let right = pose.retract_right(&(pose.inverse().adjoint() * delta));
+        assert!((left.matrix() - right.matrix()).norm() < 1e-9);
+    }
+
+    #[test]
+    fn interpolation_hits_end_points() {
+        let start = sample_pose();
+        let end = SE3::exp(&Vector6::new(-1.0, 0.5, 2.0, -0.3, 0.6, 0.1));
+        assert!((start.interpolate(&end, 0.0).matrix() - start.matrix()).norm() < 1e-9);
+        assert!((start.interpolate(&end, 1.0).matrix() - end.matrix()).norm() < 1e-9);
+        // 中点将相对位姿平分
+        let mid = start.interpolate(&end, 0.5);
+        assert!((mid.inverse().compose(&end).matrix() - start.inverse().compose(&mid).matrix()).norm() < 1e-9);
+        let expected = start.compose(&SE3::exp(&(0.5 * start.inverse().compose(&end).log())));
+        assert!((mid.matrix() - expected.matrix()).norm() < 1e-9);
+    }
+}
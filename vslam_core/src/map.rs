@@ -0,0 +1,345 @@
+use crate::feature::Descriptor;
+use crate::keyframe::KeyFrame;
+use crate::map_point::MapPoint;
+use nalgebra::Vector3;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// 前端与后端共享的地图
+pub type SharedMap<D> = Arc<RwLock<Map<D>>>;
+
+/// 地图，持有全部关键帧和地图点
+/// 关键帧与地图点之间的观测关系只能通过Map修改，保证双向一致
+pub struct Map<D> {
+    keyframes: BTreeMap<usize, KeyFrame<D>>,
+    map_points: BTreeMap<usize, MapPoint<D>>,
+    next_map_point_id: usize,
+}
+
+impl<D> Default for Map<D> {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+impl<D> Map<D> {
+    pub fn new() -> Self {
+        Map {
+            keyframes: BTreeMap::new(),
+            map_points: BTreeMap::new(),
+            next_map_point_id: 0,
+        }
+    }
+
+    /// 转为可在线程间共享的地图
+    pub fn into_shared(self) -> SharedMap<D> {
+        Arc::new(RwLock::new(self))
+    }
+
+    pub fn num_keyframes(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn num_map_points(&self) -> usize {
+        self.map_points.len()
+    }
+
+    pub fn keyframe(&self, id: usize) -> Option<&KeyFrame<D>> {
+        self.keyframes.get(&id)
+    }
+
+    pub fn keyframe_mut(&mut self, id: usize) -> Option<&mut KeyFrame<D>> {
+        self.keyframes.get_mut(&id)
+    }
+
+    pub fn keyframes(&self) -> impl Iterator<Item = &KeyFrame<D>> {
+        self.keyframes.values()
+    }
+
+    pub fn map_point(&self, id: usize) -> Option<&MapPoint<D>> {
+        self.map_points.get(&id)
+    }
+
+    pub fn map_point_mut(&mut self, id: usize) -> Option<&mut MapPoint<D>> {
+        self.map_points.get_mut(&id)
+    }
+
+    pub fn map_points(&self) -> impl Iterator<Item = &MapPoint<D>> {
+        self.map_points.values()
+    }
+
+    /// 插入关键帧，关键帧中已有的地图点关联会被清空
+    /// 同id的旧关键帧会先被删除
+    pub fn insert_keyframe(&mut self, mut keyframe: KeyFrame<D>) {
+        self.erase_keyframe(keyframe.id());
+        for index in 0..keyframe.map_points().len() {
+            keyframe.set_map_point(index, None);
+        }
+        self.keyframes.insert(keyframe.id(), keyframe);
+    }
+
+    /// 新建地图点，返回其id
+    pub fn insert_map_point(&mut self, position: Vector3<f64>, descriptor: D) -> usize {
+        let id = self.next_map_point_id;
+        self.next_map_point_id += 1;
+        self.map_points
+            .insert(id, MapPoint::new(id, position, descriptor));
+        id
+    }
+
+    /// 添加观测：地图点map_point_id被关键帧keyframe_id的第index个特征观测到
+    /// 该特征原先关联的地图点和该地图点在该关键帧中的原观测都会被替换
+    /// 关键帧、地图点或特征不存在时返回false
+    pub fn add_observation(
+        &mut self,
+        map_point_id: usize,
+        keyframe_id: usize,
+        index: usize,
+    ) -> bool {
+        let valid = self.map_points.contains_key(&map_point_id)
+            && self
+                .keyframes
+                .get(&keyframe_id)
+                .is_some_and(|keyframe| index < keyframe.map_points().len());
+        if !valid {
+            return false;
+        }
+
+        // 特征原先关联的地图点
+        if let Some(old_point) = self.keyframes[&keyframe_id].map_point(index) {
+            self.erase_observation(old_point, keyframe_id);
+        }
+        // 地图点在该关键帧中的原观测
+        self.erase_observation(map_point_id, keyframe_id);
+
+        if let Some(keyframe) = self.keyframes.get_mut(&keyframe_id) {
+            keyframe.set_map_point(index, Some(map_point_id));
+        }
+        if let Some(map_point) = self.map_points.get_mut(&map_point_id) {
+            map_point.add_observation(keyframe_id, index);
+        }
+        true
+    }
+
+    /// 删除观测，同时清除关键帧中的关联
+    pub fn erase_observation(&mut self, map_point_id: usize, keyframe_id: usize) -> bool {
+        let index = match self.map_points.get_mut(&map_point_id) {
+            Some(map_point) => map_point.erase_observation(keyframe_id),
+            None => None,
+        };
+        match (index, self.keyframes.get_mut(&keyframe_id)) {
+            (Some(index), Some(keyframe)) => {
+                keyframe.set_map_point(index, None);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 删除地图点，同时清除所有关键帧中的关联
+    pub fn erase_map_point(&mut self, id: usize) -> Option<MapPoint<D>> {
+        let map_point = self.map_points.remove(&id)?;
+        for (keyframe_id, &index) in map_point.observations() {
+            if let Some(keyframe) = self.keyframes.get_mut(keyframe_id) {
+                keyframe.set_map_point(index, None);
+            }
+        }
+        Some(map_point)
+    }
+
+    /// 删除关键帧，同时删除其观测，不再被任何关键帧观测的地图点一并删除
+    pub fn erase_keyframe(&mut self, id: usize) -> Option<KeyFrame<D>> {
+        let keyframe = self.keyframes.remove(&id)?;
+        for &map_point_id in keyframe.map_points().iter().flatten() {
+            let orphan = match self.map_points.get_mut(&map_point_id) {
+                Some(map_point) => {
+                    map_point.erase_observation(id);
+                    map_point.num_observations() == 0
+                }
+                None => false,
+            };
+            if orphan {
+                self.map_points.remove(&map_point_id);
+            }
+        }
+        Some(keyframe)
+    }
+
+    /// 剔除观测数少于min_observations的地图点，返回被删除的id
+    pub fn cull_map_points(&mut self, min_observations: usize) -> Vec<usize> {
+        let culled: Vec<usize> = self
+            .map_points
+            .values()
+            .filter(|map_point| map_point.num_observations() < min_observations)
+            .map(|map_point| map_point.id())
+            .collect();
+        for &id in &culled {
+            self.erase_map_point(id);
+        }
+        culled
+    }
+
+    /// 剔除冗余关键帧，返回被删除的id
+    /// 若关键帧中至少redundant_ratio比例的地图点还被其他至少min_other_observations个关键帧观测到，则认为冗余
+    /// protected中的关键帧（如第一帧）不会被删除
+    pub fn cull_redundant_keyframes(
+        &mut self,
+        min_other_observations: usize,
+        redundant_ratio: f64,
+        protected: &[usize],
+    ) -> Vec<usize> {
+        let mut culled = Vec::new();
+        let ids: Vec<usize> = self.keyframes.keys().copied().collect();
+        for id in ids {
+            if protected.contains(&id) {
+                continue;
+            }
+            let keyframe = &self.keyframes[&id];
+            let mut num_points = 0;
+            let mut num_redundant = 0;
+            for map_point_id in keyframe.map_points().iter().flatten() {
+                if let Some(map_point) = self.map_points.get(map_point_id) {
+                    num_points += 1;
+                    if map_point.num_observations() > min_other_observations {
+                        num_redundant += 1;
+                    }
+                }
+            }
+            if num_points > 0 && num_redundant as f64 >= redundant_ratio * num_points as f64 {
+                self.erase_keyframe(id);
+                culled.push(id);
+            }
+        }
+        culled
+    }
+
+    /// 根据观测关键帧的光心更新地图点的平均观测方向和有效距离
+    pub fn update_normal_and_depth(&mut self, map_point_id: usize) {
+        let keyframes = &self.keyframes;
+        if let Some(map_point) = self.map_points.get_mut(&map_point_id) {
+            let centers: Vec<Vector3<f64>> = map_point
+                .observations()
+                .keys()
+                .filter_map(|id| keyframes.get(id))
+                .map(|keyframe| keyframe.camera_center())
+                .collect();
+            map_point.update_normal_and_depth(&centers);
+        }
+    }
+}
+
+impl<D: Descriptor> Map<D> {
+    /// 从各观测的描述子中选出最有代表性的作为地图点描述子
+    pub fn update_descriptor(&mut self, map_point_id: usize) {
+        let keyframes = &self.keyframes;
+        if let Some(map_point) = self.map_points.get_mut(&map_point_id) {
+            let descriptors: Vec<D> = map_point
+                .observations()
+                .iter()
+                .filter_map(|(id, &index)| keyframes.get(id)?.descriptor(index).cloned())
+                .collect();
+            map_point.compute_distinctive_descriptor(&descriptors);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feature::KeyPoint;
+    use crate::lie::SE3;
+    use nalgebra::Vector2;
+
+    fn keyframe(id: usize, descriptors: Vec<[u64; 4]>) -> KeyFrame<[u64; 4]> {
+        let keypoints = vec![KeyPoint::new(Vector2::zeros()); descriptors.len()];
+        KeyFrame::new(
+            id,
+            0.0,
+            String::new(),
+            SE3::identity(),
+            keypoints,
+            descriptors,
+        )
+        .unwrap()
+    }
+
+    /// 检查双向观测关系一致
+    fn assert_consistent(map: &Map<[u64; 4]>) {
+        for map_point in map.map_points() {
+            for (&keyframe_id, &index) in map_point.observations() {
+                let keyframe = map.keyframe(keyframe_id).unwrap();
+                assert_eq!(keyframe.map_point(index), Some(map_point.id()));
+            }
+        }
+        for keyframe in map.keyframes() {
+            for (index, map_point_id) in keyframe.map_points().iter().enumerate() {
+                if let Some(id) = map_point_id {
+                    let map_point = map.map_point(*id).unwrap();
+                    assert_eq!(map_point.index_in_keyframe(keyframe.id()), Some(index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn observations_stay_consistent() {
+        let mut map = Map::new();
+        map.insert_keyframe(keyframe(0, vec![[0; 4]; 3]));
+        map.insert_keyframe(keyframe(1, vec![[0; 4]; 3]));
+        let a = map.insert_map_point(Vector3::new(0.0, 0.0, 1.0), [0; 4]);
+        let b = map.insert_map_point(Vector3::new(1.0, 0.0, 1.0), [0; 4]);
+
+        assert!(map.add_observation(a, 0, 0));
+        assert!(map.add_observation(a, 1, 2));
+        assert!(map.add_observation(b, 0, 1));
+        assert!(!map.add_observation(b, 0, 3));
+        // 同一特征改为关联b，a在关键帧0中的观测被替换
+        assert!(map.add_observation(b, 0, 0));
+        assert_eq!(map.map_point(a).unwrap().num_observations(), 1);
+        assert_eq!(map.keyframe(0).unwrap().map_point(1), None);
+        assert_consistent(&map);
+
+        map.erase_map_point(b);
+        assert_eq!(map.keyframe(0).unwrap().map_point(0), None);
+        assert_consistent(&map);
+
+        map.erase_keyframe(1);
+        assert!(map.map_point(a).is_none());
+        assert_consistent(&map);
+    }
+
+    #[test]
+    fn descriptor_is_chosen_from_observations() {
+        let mut map = Map::new();
+        map.insert_keyframe(keyframe(0, vec![[0b0011, 0, 0, 0]]));
+        map.insert_keyframe(keyframe(1, vec![[0b0111, 0, 0, 0]]));
+        map.insert_keyframe(keyframe(2, vec![[0b1111, 0, 0, 0]]));
+        let id = map.insert_map_point(Vector3::zeros(), [0; 4]);
+        for keyframe_id in 0..3 {
+            map.add_observation(id, keyframe_id, 0);
+        }
+        map.update_descriptor(id);
+        assert_eq!(map.map_point(id).unwrap().descriptor(), &[0b0111, 0, 0, 0]);
+    }
+
+    #[test]
+    fn culling_removes_weak_points_and_redundant_keyframes() {
+        let mut map = Map::new();
+        for id in 0..3 {
+            map.insert_keyframe(keyframe(id, vec![[0; 4]; 2]));
+        }
+        let shared = map.insert_map_point(Vector3::new(0.0, 0.0, 1.0), [0; 4]);
+        let single = map.insert_map_point(Vector3::new(0.0, 1.0, 1.0), [0; 4]);
+        for id in 0..3 {
+            map.add_observation(shared, id, 0);
+        }
+        map.add_observation(single, 2, 1);
+
+        assert_eq!(map.cull_map_points(2), vec![single]);
+        assert_consistent(&map);
+
+        assert_eq!(map.cull_redundant_keyframes(2, 0.9, &[0]), vec![1]);
+        assert_eq!(map.map_point(shared).unwrap().num_observations(), 2);
+        assert_consistent(&map);
+    }
+}
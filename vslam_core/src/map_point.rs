@@ -0,0 +1,127 @@
+use crate::feature::{distinctive_descriptor, Descriptor};
+use nalgebra::Vector3;
+use std::collections::BTreeMap;
+
+/// 地图点
+pub struct MapPoint<D> {
+    id: usize,                            // 编号
+    position: Vector3<f64>,               // 世界坐标系下的位置
+    descriptor: D,                        // 代表性描述子
+    normal: Vector3<f64>,                 // 平均观测方向（单位向量）
+    min_distance: f64,                    // 有效观测距离下限
+    max_distance: f64,                    // 有效观测距离上限
+    observations: BTreeMap<usize, usize>, // 关键帧id → 特征索引
+}
+
+impl<D> MapPoint<D> {
+    pub fn new(id: usize, position: Vector3<f64>, descriptor: D) -> Self {
+        MapPoint {
+            id,
+            position,
+            descriptor,
+            normal: Vector3::zeros(),
+            min_distance: 0.0,
+            max_distance: f64::INFINITY,
+            observations: BTreeMap::new(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn position(&self) -> &Vector3<f64> {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f64>) {
+        self.position = position;
+    }
+
+    pub fn descriptor(&self) -> &D {
+        &self.descriptor
+    }
+
+    pub fn set_descriptor(&mut self, descriptor: D) {
+        self.descriptor = descriptor;
+    }
+
+    /// 平均观测方向，由观测到该点的相机光心指向该点
+    pub fn normal(&self) -> &Vector3<f64> {
+        &self.normal
+    }
+
+    pub fn min_distance(&self) -> f64 {
+        self.min_distance
+    }
+
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    pub fn set_depth_range(&mut self, min_distance: f64, max_distance: f64) {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+    }
+
+    /// 判断观测距离是否在有效范围内
+    pub fn is_in_depth_range(&self, distance: f64) -> bool {
+        distance >= self.min_distance && distance <= self.max_distance
+    }
+
+    /// 关键帧id → 特征索引
+    pub fn observations(&self) -> &BTreeMap<usize, usize> {
+        &self.observations
+    }
+
+    pub fn num_observations(&self) -> usize {
+        self.observations.len()
+    }
+
+    /// 在关键帧中对应的特征索引
+    pub fn index_in_keyframe(&self, keyframe_id: usize) -> Option<usize> {
+        self.observations.get(&keyframe_id).copied()
+    }
+
+    /// 根据观测到该点的相机光心更新平均观测方向和有效距离
+    pub fn update_normal_and_depth(&mut self, camera_centers: &[Vector3<f64>]) {
+        if camera_centers.is_empty() {
+            return;
+        }
+        let mut normal = Vector3::zeros();
+        let mut min_distance = f64::INFINITY;
+        let mut max_distance: f64 = 0.0;
+        for center in camera_centers {
+            let direction = self.position - center;
+            let distance = direction.norm();
+            if distance > 0.0 {
+                normal += direction / distance;
+            }
+            min_distance = min_distance.min(distance);
+            max_distance = max_distance.max(distance);
+        }
+        if normal.norm() > 0.0 {
+            self.normal = normal.normalize();
+        }
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+    }
+
+    /// 只能由Map修改，保证双向观测关系一致
+    pub(crate) fn add_observation(&mut self, keyframe_id: usize, index: usize) {
+        self.observations.insert(keyframe_id, index);
+    }
+
+    pub(crate) fn erase_observation(&mut self, keyframe_id: usize) -> Option<usize> {
+        self.observations.remove(&keyframe_id)
+    }
+}
+
+impl<D: Descriptor> MapPoint<D> {
+    /// 从各观测的描述子中选出最有代表性的作为该点描述子
+    pub fn compute_distinctive_descriptor(&mut self, descriptors: &[D]) {
+        if let Some(descriptor) = distinctive_descriptor(descriptors) {
+            self.descriptor = descriptor;
+        }
+    }
+}
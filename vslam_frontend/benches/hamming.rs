@@ -0,0 +1,108 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use vslam_core::feature::Matcher;
+use vslam_frontend::hamming::HammingKernel;
+use vslam_frontend::matcher::BFMatcher;
+use vslam_frontend::orb::ORB;
+use vslam_frontend::rng::split_mix64;
+
+/// 每帧特征点数
+const NUM_FEATURES: usize = 2000;
+/// 汇总相对耗时时每种方法的重复次数
+const SUMMARY_RUNS: u32 = 5;
+
+/// 均匀随机的256位描述子，种子相同时结果相同
+fn descriptors(count: usize, seed: u64) -> Vec<[u64; 4]> {
+    let mut state = seed;
+    (0..count).map(|_| [0; 4].map(|_: u64| split_mix64(&mut state))).collect()
+}
+
+/// 原ORB::match_features的做法：逐对计算汉明距离的二重循环，保留最小的两个做比值测试
+fn baseline_match(query: &[[u64; 4]], train: &[[u64; 4]]) -> Vec<(usize, usize)> {
+    let ratio_threshold = 0.8;
+    let mut matches = Vec::new();
+    for (i, query_descriptor) in query.iter().enumerate() {
+        let mut best_distance = u32::MAX;
+        let mut second_best_distance = u32::MAX;
+        let mut best_index = None;
+        for (j, train_descriptor) in train.iter().enumerate() {
+            let distance = hamming_distance(query_descriptor, train_descriptor);
+            if distance < best_distance {
+                second_best_distance = best_distance;
+                best_distance = distance;
+                best_index = Some(j);
+            } else if distance < second_best_distance {
+                second_best_distance = distance;
+            }
+        }
+        if best_distance < (ratio_threshold * second_best_distance as f64) as u32 {
+            if let Some(best_j) = best_index {
+                matches.push((i, best_j));
+            }
+        }
+    }
+    matches
+}
+
+fn hamming_distance(a: &[u64; 4], b: &[u64; 4]) -> u32 {
+    let mut distance = 0;
+    for i in 0..4 {
+        distance += (a[i] ^ b[i]).count_ones();
+    }
+    distance
+}
+
+type Case<'a> = (String, Box<dyn Fn() -> usize + 'a>);
+
+fn matching(c: &mut Criterion) {
+    let query = descriptors(NUM_FEATURES, 1);
+    let train = descriptors(NUM_FEATURES, 2);
+    let matcher = BFMatcher::default();
+
+    let mut cases: Vec<Case> = vec![
+        ("baseline".to_string(), Box::new(|| baseline_match(black_box(&query), black_box(&train)).len())),
+        // 通过Descriptor trait逐对计算距离
+        ("descriptor_trait".to_string(), Box::new(|| matcher.match_all(black_box(&query), black_box(&train)).len())),
+    ];
+    for kernel in [HammingKernel::Scalar, HammingKernel::Popcnt, HammingKernel::Avx2] {
+        if kernel.is_supported() {
+            let matcher = &matcher;
+            let (query, train) = (&query, &train);
+            cases.push((
+                format!("{:?}", kernel),
+                Box::new(move || matcher.match_binary_with(kernel, black_box(query), black_box(train)).len()),
+            ));
+        }
+    }
+    // 现在的ORB匹配经由BFMatcher使用当前CPU上最快的内核
+    cases.push((
+        "orb".to_string(),
+        Box::new(|| ORB::default().match_descriptors(black_box(&query), black_box(&train)).len()),
+    ));
+
+    let mut group = c.benchmark_group("match_2000x2000");
+    group.sample_size(10);
+    for (name, run) in &cases {
+        group.bench_function(name.as_str(), |b| b.iter(run));
+    }
+    group.finish();
+
+    // 以原ORB匹配为基准的加速比
+    let time = |run: &dyn Fn() -> usize| {
+        let start = Instant::now();
+        for _ in 0..SUMMARY_RUNS {
+            black_box(run());
+        }
+        start.elapsed() / SUMMARY_RUNS
+    };
+    let baseline: Duration = time(&*cases[0].1);
+    println!("\nspeedup over baseline ({:?} per match_2000x2000):", baseline);
+    for (name, run) in &cases[1..] {
+        let elapsed = time(&**run);
+        println!("  {:<16} {:>6.1}x", name, baseline.as_secs_f64() / elapsed.as_secs_f64());
+    }
+}
+
+criterion_group!(benches, matching);
+criterion_main!(benches);
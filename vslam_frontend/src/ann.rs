@@ -0,0 +1,422 @@
+use crate::rng::split_mix64;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use vslam_core::feature::Descriptor;
+
+/// 近邻查询结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Neighbor {
+    pub index: usize,  // 在建立索引的描述子中的序号
+    pub distance: f64, // 描述子距离
+}
+
+/// 近似最近邻索引的公共接口
+pub trait NearestNeighborIndex<D> {
+    /// 索引中的描述子数量
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 最多k个近邻，按距离从小到大排列
+    fn knn_search(&self, query: &D, k: usize) -> Vec<Neighbor>;
+
+    /// 距离不超过radius的近邻，按距离从小到大排列
+    fn radius_search(&self, query: &D, radius: f64) -> Vec<Neighbor>;
+}
+
+/// 候选点计算精确距离后排序
+fn rank<D: Descriptor>(descriptors: &[D], query: &D, candidates: impl Iterator<Item = usize>) -> Vec<Neighbor> {
+    let mut neighbors: Vec<Neighbor> = candidates
+        .map(|index| Neighbor { index, distance: query.distance(&descriptors[index]) })
+        .collect();
+    neighbors.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.index.cmp(&b.index)));
+    neighbors
+}
+
+/// 多探针LSH参数
+#[derive(Clone, Copy, Debug)]
+pub struct LshParams {
+    pub tables: usize,      // 哈希表数量
+    pub key_bits: usize,    // 每个哈希键取描述子中的位数
+    pub probe_level: usize, // 多探针时翻转的最多位数，0为只查询原桶
+    pub seed: u64,          // 选取哈希位的种子
+}
+
+impl Default for LshParams {
+    fn default() -> Self {
+        LshParams { tables: 6, key_bits: 14, probe_level: 1, seed: 0 }
+    }
+}
+
+/// 二进制描述子的多探针LSH索引
+/// 每个哈希表随机选取key_bits个位作为键，查询时还会探测键相差不超过probe_level位的桶
+pub struct LshIndex<const N: usize> {
+    descriptors: Vec<[u64; N]>,
+    params: LshParams,
+    bits: Vec<Vec<usize>>,                  // 每个表选取的位序号
+    tables: Vec<HashMap<u32, Vec<usize>>>, // 键 → 描述子序号
+}
+
+impl<const N: usize> LshIndex<N> {
+    pub fn new(descriptors: Vec<[u64; N]>, params: LshParams) -> Self {
+        // N为0时没有可选的位，所有描述子落在同一个桶中
+        let key_bits = params.key_bits.clamp(1, 32).min(N * 64);
+        let params = LshParams { key_bits, ..params };
+        let mut state = params.seed;
+        let bits: Vec<Vec<usize>> = (0..params.tables)
+            .map(|_| {
+                // 不重复地选取key_bits个位
+                let mut positions: Vec<usize> = (0..N * 64).collect();
+                for i in 0..key_bits {
+                    let j = i + (split_mix64(&mut state) % (positions.len() - i) as u64) as usize;
+                    positions.swap(i, j);
+                }
+                positions.truncate(key_bits);
+                positions
+            })
+            .collect();
+
+        let mut tables = vec![HashMap::new(); params.tables];
+        for (index, descriptor) in descriptors.iter().enumerate() {
+            for (table, bits) in tables.iter_mut().zip(bits.iter()) {
+                table.entry(hash_key(descriptor, bits)).or_insert_with(Vec::new).push(index);
+            }
+        }
+
+        LshIndex { descriptors, params, bits, tables }
+    }
+
+    /// 所有探测桶中的候选描述子，已去重
+    fn candidates(&self, query: &[u64; N]) -> Vec<usize> {
+        let mut visited = HashSet::new();
+        let mut candidates = Vec::new();
+        for (table, bits) in self.tables.iter().zip(self.bits.iter()) {
+            let key = hash_key(query, bits);
+            for probe in probes(key, self.params.key_bits, self.params.probe_level) {
+                for &index in table.get(&probe).into_iter().flatten() {
+                    if visited.insert(index) {
+                        candidates.push(index);
+                    }
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl<const N: usize> NearestNeighborIndex<[u64; N]> for LshIndex<N> {
+    fn len(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    fn knn_search(&self, query: &[u64; N], k: usize) -> Vec<Neighbor> {
+        let mut neighbors = rank(&self.descriptors, query, self.candidates(query).into_iter());
+        neighbors.truncate(k);
+        neighbors
+    }
+
+    fn radius_search(&self, query: &[u64; N], radius: f64) -> Vec<Neighbor> {
+        let mut neighbors = rank(&self.descriptors, query, self.candidates(query).into_iter());
+        neighbors.retain(|neighbor| neighbor.distance <= radius);
+        neighbors
+    }
+}
+
+/// 取描述子中选定的位组成哈希键
+fn hash_key<const N: usize>(descriptor: &[u64; N], bits: &[usize]) -> u32 {
+    bits.iter()
+        .enumerate()
+        .fold(0, |key, (i, &bit)| key | ((((descriptor[bit / 64] >> (bit % 64)) & 1) as u32) << i))
+}
+
+/// 与key相差不超过level位的所有键，包括key本身
+fn probes(key: u32, key_bits: usize, level: usize) -> Vec<u32> {
+    let mut keys = vec![key];
+    let mut frontier = vec![(key, 0usize)];
+    for _ in 0..level {
+        let mut next = Vec::new();
+        for &(probe, start) in &frontier {
+            // 只翻转比上次更高的位，避免重复
+            for bit in start..key_bits {
+                let flipped = probe ^ (1 << bit);
+                keys.push(flipped);
+                next.push((flipped, bit + 1));
+            }
+        }
+        frontier = next;
+    }
+    keys
+}
+
+/// 随机k-d树森林参数
+#[derive(Clone, Copy, Debug)]
+pub struct KdForestParams {
+    pub trees: usize,      // 树的数量
+    pub leaf_size: usize,  // 叶节点最多包含的描述子数
+    pub max_checks: usize, // 每次查询最多计算距离的描述子数，越大越精确
+    pub seed: u64,         // 选取划分维度的种子
+}
+
+impl Default for KdForestParams {
+    fn default() -> Self {
+        KdForestParams { trees: 4, leaf_size: 10, max_checks: 512, seed: 0 }
+    }
+}
+
+/// 划分维度从方差最大的若干维中随机选取
+const KD_RANDOM_DIMENSIONS: usize = 5;
+/// 估计方差时最多使用的样本数
+const KD_VARIANCE_SAMPLES: usize = 100;
+
+enum KdNode {
+    Leaf(Vec<usize>),
+    Split { dimension: usize, value: f32, left: usize, right: usize },
+}
+
+/// 浮点描述子的随机k-d树森林，与FLANN相同
+/// 各棵树在方差最大的几维中随机选取划分维度，查询时所有树共用一个按下界排序的优先队列
+pub struct KdForest<D> {
+    descriptors: Vec<D>,
+    params: KdForestParams,
+    nodes: Vec<KdNode>, // 所有树的节点
+    roots: Vec<usize>,
+}
+
+/// 优先队列中的待搜索分支，按距离下界从小到大出队
+struct Branch {
+    bound: f32,
+    node: usize,
+}
+
+impl PartialEq for Branch {
+    fn eq(&self, other: &Self) -> bool {
+        self.bound == other.bound
+    }
+}
+
+impl Eq for Branch {}
+
+impl PartialOrd for Branch {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Branch {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bound.total_cmp(&self.bound)
+    }
+}
+
+impl<D: Descriptor + AsRef<[f32]>> KdForest<D> {
+    pub fn new(descriptors: Vec<D>, params: KdForestParams) -> Self {
+        let mut forest = KdForest { descriptors, params, nodes: Vec::new(), roots: Vec::new() };
+        let mut state = params.seed;
+        for _ in 0..params.trees.max(1) {
+            let indices: Vec<usize> = (0..forest.descriptors.len()).collect();
+            let root = forest.build(indices, &mut state);
+            forest.roots.push(root);
+        }
+        forest
+    }
+
+    /// 递归建树，返回节点序号
+    fn build(&mut self, mut indices: Vec<usize>, state: &mut u64) -> usize {
+        if indices.len() <= self.params.leaf_size.max(1) {
+            self.nodes.push(KdNode::Leaf(indices));
+            return self.nodes.len() - 1;
+        }
+
+        let dimensions = self.descriptors[indices[0]].as_ref().len();
+        let samples = &indices[..indices.len().min(KD_VARIANCE_SAMPLES)];
+        let mut mean = vec![0.0f64; dimensions];
+        for &index in samples {
+            for (m, &value) in mean.iter_mut().zip(self.descriptors[index].as_ref()) {
+                *m += value as f64;
+            }
+        }
+        mean.iter_mut().for_each(|m| *m /= samples.len() as f64);
+        let mut variance = vec![0.0f64; dimensions];
+        for &index in samples {
+            for ((v, m), &value) in variance.iter_mut().zip(mean.iter()).zip(self.descriptors[index].as_ref()) {
+                *v += (value as f64 - m).powi(2);
+            }
+        }
+
+        let mut order: Vec<usize> = (0..dimensions).collect();
+        order.sort_by(|&a, &b| variance[b].total_cmp(&variance[a]));
+        let top = KD_RANDOM_DIMENSIONS.min(dimensions);
+        let dimension = order[(split_mix64(state) % top as u64) as usize];
+        let value = mean[dimension] as f32;
+
+        let (left, right): (Vec<usize>, Vec<usize>) =
+            indices.iter().partition(|&&index| self.descriptors[index].as_ref()[dimension] < value);
+        // 所有描述子在该维相同时无法划分
+        if left.is_empty() || right.is_empty() {
+            indices.shrink_to_fit();
+            self.nodes.push(KdNode::Leaf(indices));
+            return self.nodes.len() - 1;
+        }
+
+        let left = self.build(left, state);
+        let right = self.build(right, state);
+        self.nodes.push(KdNode::Split { dimension, value, left, right });
+        self.nodes.len() - 1
+    }
+
+    /// 按下界从小到大访问叶节点
+    /// radius为None时收集至多max_checks个候选，否则访问下界不超过radius的所有分支
+    fn candidates(&self, query: &D, radius: Option<f64>) -> Vec<usize> {
+        let query_values = query.as_ref();
+        let mut visited = HashSet::new();
+        let mut candidates = Vec::new();
+        let mut queue: BinaryHeap<Branch> = self.roots.iter().map(|&node| Branch { bound: 0.0, node }).collect();
+
+        while let Some(Branch { bound, node }) = queue.pop() {
+            match radius {
+                // 单个维度的差不超过欧氏距离，下界超过半径的分支中没有近邻
+                Some(radius) if bound as f64 > radius => break,
+                None if candidates.len() >= self.params.max_checks => break,
+                _ => {}
+            }
+            let mut node = node;
+            loop {
+                match &self.nodes[node] {
+                    KdNode::Leaf(indices) => {
+                        for &index in indices {
+                            if visited.insert(index) {
+                                candidates.push(index);
+                            }
+                        }
+                        break;
+                    }
+                    KdNode::Split { dimension, value, left, right } => {
+                        let difference = query_values[*dimension] - value;
+                        let (near, far) = if difference < 0.0 { (*left, *right) } else { (*right, *left) };
+                        queue.push(Branch { bound: bound.max(difference.abs()), node: far });
+                        node = near;
+                    }
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl<D: Descriptor + AsRef<[f32]>> NearestNeighborIndex<D> for KdForest<D> {
+    fn len(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    fn knn_search(&self, query: &D, k: usize) -> Vec<Neighbor> {
+        let mut neighbors = rank(&self.descriptors, query, self.candidates(query, None).into_iter());
+        neighbors.truncate(k);
+        neighbors
+    }
+
+    /// 不受max_checks限制，结果与穷举相同
+    fn radius_search(&self, query: &D, radius: f64) -> Vec<Neighbor> {
+        let mut neighbors = rank(&self.descriptors, query, self.candidates(query, Some(radius)).into_iter());
+        neighbors.retain(|neighbor| neighbor.distance <= radius);
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_words(state: &mut u64, count: usize) -> Vec<[u64; 4]> {
+        (0..count).map(|_| [0; 4].map(|_: u64| split_mix64(state))).collect()
+    }
+
+    /// 暴力搜索的最近邻
+    fn brute_force<D: Descriptor>(descriptors: &[D], query: &D) -> usize {
+        rank(descriptors, query, 0..descriptors.len())[0].index
+    }
+
+    #[test]
+    fn lsh_finds_planted_neighbors() {
+        let mut state = 1;
+        let descriptors = random_words(&mut state, 5000);
+        // 在已有描述子上翻转少量位作为查询
+        let queries: Vec<[u64; 4]> = descriptors
+            .iter()
+            .step_by(50)
+            .map(|descriptor| {
+                let mut query = *descriptor;
+                for _ in 0..8 {
+                    let bit = split_mix64(&mut state) % 256;
+                    query[(bit / 64) as usize] ^= 1 << (bit % 64);
+                }
+                query
+            })
+            .collect();
+
+        let index = LshIndex::new(descriptors.clone(), LshParams::default());
+        assert_eq!(index.len(), 5000);
+        let found = queries
+            .iter()
+            .filter(|query| index.knn_search(query, 1).first().map(|n| n.index) == Some(brute_force(&descriptors, query)))
+            .count();
+        assert!(found * 10 >= queries.len() * 9, "{} / {}", found, queries.len());
+
+        let neighbors = index.radius_search(&descriptors[0], 0.0);
+        assert_eq!(neighbors, vec![Neighbor { index: 0, distance: 0.0 }]);
+
+        let empty = LshIndex::<0>::new(vec![[]; 3], LshParams::default());
+        assert_eq!(empty.knn_search(&[], 5).len(), 3);
+    }
+
+    #[test]
+    fn probes_cover_hamming_ball() {
+        let keys = probes(0b1010, 4, 2);
+        // 1 + 4 + 6
+        assert_eq!(keys.len(), 11);
+        let mut unique = keys.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 11);
+    }
+
+    #[test]
+    fn kd_forest_finds_nearest_neighbors() {
+        let mut state = 3;
+        let mut uniform = || (split_mix64(&mut state) % 10000) as f32 / 10000.0;
+        // 若干簇，模拟真实描述子的分布
+        let centers: Vec<[f32; 32]> = (0..50).map(|_| [0.0; 32].map(|_: f32| uniform())).collect();
+        let descriptors: Vec<[f32; 32]> = (0..4000)
+            .map(|i| {
+                let center = &centers[i % centers.len()];
+                std::array::from_fn(|d| center[d] + 0.1 * uniform())
+            })
+            .collect();
+        let queries: Vec<[f32; 32]> = descriptors
+            .iter()
+            .step_by(40)
+            .map(|descriptor| descriptor.map(|value| value + 0.01 * uniform()))
+            .collect();
+
+        let index = KdForest::new(descriptors.clone(), KdForestParams::default());
+        let found = queries
+            .iter()
+            .filter(|query| index.knn_search(query, 1)[0].index == brute_force(&descriptors, query))
+            .count();
+        assert!(found * 10 >= queries.len() * 9, "{} / {}", found, queries.len());
+
+        let neighbors = index.knn_search(&queries[0], 5);
+        assert_eq!(neighbors.len(), 5);
+        assert!(neighbors.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+        let radius = neighbors[2].distance;
+        assert!(index.radius_search(&queries[0], radius).iter().all(|neighbor| neighbor.distance <= radius));
+
+        // 半径内的描述子远多于max_checks时仍全部返回
+        let index = KdForest::new(descriptors.clone(), KdForestParams { max_checks: 32, ..KdForestParams::default() });
+        let radius = 1.5;
+        let expected = descriptors.iter().filter(|descriptor| descriptor.distance(&queries[0]) <= radius).count();
+        assert!(expected > 32, "{}", expected);
+        assert_eq!(index.radius_search(&queries[0], radius).len(), expected);
+    }
+}
@@ -0,0 +1,405 @@
+use crate::ransac::{Estimator, Ransac};
+use crate::two_view::eight_point;
+use nalgebra::{Matrix3, Matrix4, Rotation3, RowVector4, SMatrix, UnitQuaternion, Vector2, Vector3};
+use vslam_core::camera::Camera;
+use vslam_core::feature::KeyPoint;
+use vslam_core::lie::SE3;
+
+/// 三角化深度超过基线的该倍数时视为无穷远点，不参与正深度检查
+const MAX_DEPTH_RATIO: f64 = 50.0;
+
+/// 五点法本质矩阵估计，外层为RANSAC
+#[derive(Clone, Copy, Debug)]
+pub struct EssentialEstimator {
+    pub threshold: f64,        // 内点的Sampson距离阈值，单位像素
+    pub confidence: f64,       // 自适应迭代次数所用的置信度
+    pub max_iterations: usize, // 迭代次数上限
+    pub seed: u64,             // 随机采样的种子
+}
+
+impl Default for EssentialEstimator {
+    fn default() -> Self {
+        EssentialEstimator { threshold: 1.0, confidence: 0.999, max_iterations: 1000, seed: 0 }
+    }
+}
+
+/// 两帧之间的相对位姿
+#[derive(Clone, Debug)]
+pub struct RelativePose {
+    pub essential: Matrix3<f64>, // 本质矩阵，x2^T·E·x1 = 0
+    pub pose: SE3,               // T21，第一帧相机坐标系到第二帧，平移为单位长度
+    pub inliers: Vec<bool>,      // 与输入的匹配一一对应，同时满足极线约束和正深度
+}
+
+impl EssentialEstimator {
+    /// 由特征点匹配估计相对位姿，matches为(第一帧特征序号, 第二帧特征序号)
+    /// 匹配少于5对或找不到有效模型时返回None
+    pub fn estimate<C: Camera>(
+        &self,
+        camera: &C,
+        keypoints1: &[KeyPoint],
+        keypoints2: &[KeyPoint],
+        matches: &[(usize, usize)],
+    ) -> Option<RelativePose> {
+        let normalize = |keypoint: &KeyPoint| {
+            let bearing = camera.unproject(&keypoint.location);
+            Vector2::new(bearing.x / bearing.z, bearing.y / bearing.z)
+        };
+        let points1: Vec<Vector2<f64>> = matches.iter().map(|&(i, _)| normalize(&keypoints1[i])).collect();
+        let points2: Vec<Vector2<f64>> = matches.iter().map(|&(_, j)| normalize(&keypoints2[j])).collect();
+        let k = camera.intrinsic_matrix();
+        self.estimate_normalized(&points1, &points2, (k[(0, 0)] + k[(1, 1)]) / 2.0)
+    }
+
+    /// 由归一化平面上的对应点估计相对位姿，focal用于把像素阈值换算到归一化平面
+    pub fn estimate_normalized(&self, points1: &[Vector2<f64>], points2: &[Vector2<f64>], focal: f64) -> Option<RelativePose> {
+        let n = points1.len().min(points2.len());
+        let solver = EssentialSolver { points1: &points1[..n], points2: &points2[..n] };
+        let ransac = Ransac {
+            threshold: (self.threshold / focal).powi(2),
+            confidence: self.confidence,
+            max_iterations: self.max_iterations,
+            seed: self.seed,
+            ..Ransac::default()
+        };
+        let result = ransac.run(&solver)?;
+        let (pose, inliers) = recover_pose(&result.model, points1, points2, &result.inliers)?;
+        Some(RelativePose { essential: result.model, pose, inliers })
+    }
+}
+
+/// 本质矩阵的最小解算器，残差为Sampson距离的平方
+struct EssentialSolver<'a> {
+    points1: &'a [Vector2<f64>],
+    points2: &'a [Vector2<f64>],
+}
+
+impl Estimator for EssentialSolver<'_> {
+    type Model = Matrix3<f64>;
+
+    fn sample_size(&self) -> usize {
+        5
+    }
+
+    fn num_data(&self) -> usize {
+        self.points1.len()
+    }
+
+    fn fit(&self, sample: &[usize]) -> Vec<Matrix3<f64>> {
+        let sample1: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points1[i]).collect();
+        let sample2: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points2[i]).collect();
+        five_point(&sample1, &sample2)
+    }
+
+    fn residual(&self, essential: &Matrix3<f64>, index: usize) -> f64 {
+        sampson_error(essential, &self.points1[index], &self.points2[index])
+    }
+
+    /// 内点上的八点法，再投影到奇异值为(1, 1, 0)的本质矩阵流形上
+    fn refine(&self, _essential: &Matrix3<f64>, inliers: &[usize]) -> Vec<Matrix3<f64>> {
+        let inliers1: Vec<Vector2<f64>> = inliers.iter().map(|&i| self.points1[i]).collect();
+        let inliers2: Vec<Vector2<f64>> = inliers.iter().map(|&i| self.points2[i]).collect();
+        let Some(fundamental) = eight_point(&inliers1, &inliers2) else {
+            return Vec::new();
+        };
+        let mut svd = fundamental.svd(true, true);
+        svd.singular_values = Vector3::new(1.0, 1.0, 0.0);
+        svd.recompose().ok().map(|essential| essential / essential.norm()).into_iter().collect()
+    }
+}
+
+/// 点对到本质矩阵的Sampson距离的平方，归一化平面上
+pub fn sampson_error(essential: &Matrix3<f64>, point1: &Vector2<f64>, point2: &Vector2<f64>) -> f64 {
+    let x1 = point1.push(1.0);
+    let x2 = point2.push(1.0);
+    let ex1 = essential * x1;
+    let etx2 = essential.transpose() * x2;
+    let error = x2.dot(&ex1);
+    let denominator = ex1.x * ex1.x + ex1.y * ex1.y + etx2.x * etx2.x + etx2.y * etx2.y;
+    if denominator <= 0.0 {
+        return f64::INFINITY;
+    }
+    error * error / denominator
+}
+
+/// 三元多项式的单项式指数(x, y, z)，次数不超过3
+/// 前10个为Gröbner基的首项，后10个为商环的基 [x², xy, y², xz, yz, z², x, y, z, 1]
+const MONOMIALS: [[u8; 3]; 20] = [
+    [3, 0, 0], [2, 1, 0], [1, 2, 0], [0, 3, 0], [2, 0, 1], [1, 1, 1], [0, 2, 1], [1, 0, 2], [0, 1, 2], [0, 0, 3],
+    [2, 0, 0], [1, 1, 0], [0, 2, 0], [1, 0, 1], [0, 1, 1], [0, 0, 2], [1, 0, 0], [0, 1, 0], [0, 0, 1], [0, 0, 0],
+];
+
+/// 按MONOMIALS排列系数的多项式
+type Polynomial = [f64; 20];
+
+fn monomial_index(exponents: [u8; 3]) -> usize {
+    MONOMIALS
+        .iter()
+        .position(|monomial| *monomial == exponents)
+        .expect("polynomial degree exceeds 3")
+}
+
+fn multiply(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    let mut product = [0.0; 20];
+    for (i, &ca) in a.iter().enumerate().filter(|(_, c)| **c != 0.0) {
+        for (j, &cb) in b.iter().enumerate().filter(|(_, c)| **c != 0.0) {
+            let exponents = [0, 1, 2].map(|k| MONOMIALS[i][k] + MONOMIALS[j][k]);
+            product[monomial_index(exponents)] += ca * cb;
+        }
+    }
+    product
+}
+
+fn add(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn subtract(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn scale(a: &Polynomial, s: f64) -> Polynomial {
+    a.map(|c| c * s)
+}
+
+/// 五点法，与Nistér相同的约束，用Stewenius的Gröbner基求解
+/// E = x·X + y·Y + z·Z + W位于5个极线约束的4维零空间中，
+/// 再由det(E) = 0和2·E·Eᵀ·E - tr(E·Eᵀ)·E = 0得到10个三次方程，
+/// 消元后作用矩阵的实特征值对应(x, y, z)
+/// 返回至多10个本质矩阵，只使用前5对点，不足5对时无解
+pub fn five_point(points1: &[Vector2<f64>], points2: &[Vector2<f64>]) -> Vec<Matrix3<f64>> {
+    if points1.len() < 5 || points2.len() < 5 {
+        return Vec::new();
+    }
+
+    // 极线约束x2ᵀ·E·x1 = 0，E按行优先展开
+    let mut constraints = SMatrix::<f64, 9, 9>::zeros();
+    for i in 0..5 {
+        let (x1, x2) = (points1[i].push(1.0), points2[i].push(1.0));
+        for r in 0..3 {
+            for c in 0..3 {
+                constraints[(i, r * 3 + c)] = x2[r] * x1[c];
+            }
+        }
+    }
+    let Some(v_t) = constraints.svd(false, true).v_t else {
+        return Vec::new();
+    };
+    let basis: [Matrix3<f64>; 4] = std::array::from_fn(|k| Matrix3::from_fn(|r, c| v_t[(5 + k, r * 3 + c)]));
+
+    // E的每个元素是x, y, z的一次多项式
+    let entry = |r: usize, c: usize| {
+        let mut polynomial = [0.0; 20];
+        polynomial[monomial_index([1, 0, 0])] = basis[0][(r, c)];
+        polynomial[monomial_index([0, 1, 0])] = basis[1][(r, c)];
+        polynomial[monomial_index([0, 0, 1])] = basis[2][(r, c)];
+        polynomial[monomial_index([0, 0, 0])] = basis[3][(r, c)];
+        polynomial
+    };
+    let e: [[Polynomial; 3]; 3] = std::array::from_fn(|r| std::array::from_fn(|c| entry(r, c)));
+
+    let mut equations = SMatrix::<f64, 10, 20>::zeros();
+    let cofactor = |r1: usize, r2: usize, c1: usize, c2: usize| {
+        subtract(&multiply(&e[r1][c1], &e[r2][c2]), &multiply(&e[r1][c2], &e[r2][c1]))
+    };
+    let determinant = add(
+        &subtract(&multiply(&e[0][0], &cofactor(1, 2, 1, 2)), &multiply(&e[0][1], &cofactor(1, 2, 0, 2))),
+        &multiply(&e[0][2], &cofactor(1, 2, 0, 1)),
+    );
+    equations.row_mut(0).copy_from_slice(&determinant);
+
+    // E·Eᵀ
+    let eet: [[Polynomial; 3]; 3] = std::array::from_fn(|r| {
+        std::array::from_fn(|c| (0..3).fold([0.0; 20], |sum, k| add(&sum, &multiply(&e[r][k], &e[c][k]))))
+    });
+    let trace = add(&add(&eet[0][0], &eet[1][1]), &eet[2][2]);
+    for (r, row) in e.iter().enumerate() {
+        for (c, entry) in row.iter().enumerate() {
+            let eete = (0..3).fold([0.0; 20], |sum, k| add(&sum, &multiply(&eet[r][k], &e[k][c])));
+            let equation = subtract(&scale(&eete, 2.0), &multiply(&trace, entry));
+            equations.row_mut(1 + r * 3 + c).copy_from_slice(&equation);
+        }
+    }
+
+    // 消元得到[I | B]，首项 = -B·基
+    let leading = equations.fixed_view::<10, 10>(0, 0).into_owned();
+    let Some(b) = leading.lu().solve(&equations.fixed_view::<10, 10>(0, 10).into_owned()) else {
+        return Vec::new();
+    };
+
+    // 乘以x的作用矩阵：x²→x³, xy→x²y, y²→xy², xz→x²z, yz→xyz, z²→xz², x→x², y→xy, z→xz, 1→x
+    let mut action = SMatrix::<f64, 10, 10>::zeros();
+    for (row, leading_row) in [0, 1, 2, 4, 5, 7].into_iter().enumerate() {
+        action.set_row(row, &-b.row(leading_row));
+    }
+    action[(6, 0)] = 1.0;
+    action[(7, 1)] = 1.0;
+    action[(8, 3)] = 1.0;
+    action[(9, 6)] = 1.0;
+
+    let mut solutions = Vec::new();
+    for eigenvalue in action.complex_eigenvalues().iter() {
+        if eigenvalue.im.abs() > 1e-8 * (1.0 + eigenvalue.re.abs()) {
+            continue;
+        }
+        let shifted = action - SMatrix::<f64, 10, 10>::identity() * eigenvalue.re;
+        let Some(v_t) = shifted.svd(false, true).v_t else {
+            continue;
+        };
+        let vector = v_t.row(9);
+        if vector[9].abs() < 1e-12 {
+            continue;
+        }
+        let (x, y, z) = (vector[6] / vector[9], vector[7] / vector[9], vector[8] / vector[9]);
+        let essential = basis[0] * x + basis[1] * y + basis[2] * z + basis[3];
+        let norm = essential.norm();
+        if norm > 0.0 {
+            solutions.push(essential / norm);
+        }
+    }
+    solutions
+}
+
+/// 由本质矩阵分解出四组(R, t)，选取mask中三角化后位于两相机前方的点最多的一组
+/// 返回T21和同时满足mask与正深度的内点
+pub fn recover_pose(
+    essential: &Matrix3<f64>,
+    points1: &[Vector2<f64>],
+    points2: &[Vector2<f64>],
+    mask: &[bool],
+) -> Option<(SE3, Vec<bool>)> {
+    let svd = essential.svd(true, true);
+    let mut u = svd.u?;
+    let mut v_t = svd.v_t?;
+    if u.determinant() < 0.0 {
+        u = -u;
+    }
+    if v_t.determinant() < 0.0 {
+        v_t = -v_t;
+    }
+    let w = Matrix3::new(0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0);
+    let translation: Vector3<f64> = u.column(2).into_owned();
+
+    let mut best: Option<(SE3, Vec<bool>, usize)> = None;
+    for rotation in [u * w * v_t, u * w.transpose() * v_t] {
+        let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+        for t in [translation, -translation] {
+            let pose = SE3::new(rotation, t);
+            let front: Vec<bool> = mask
+                .iter()
+                .enumerate()
+                .map(|(i, &inlier)| inlier && is_in_front(&pose, &points1[i], &points2[i]))
+                .collect();
+            let count = front.iter().filter(|&&inlier| inlier).count();
+            if best.as_ref().is_none_or(|(_, _, best_count)| count > *best_count) {
+                best = Some((pose, front, count));
+            }
+        }
+    }
+    best.filter(|(_, _, count)| *count > 0).map(|(pose, front, _)| (pose, front))
+}
+
+/// 三角化的点在两相机前方且不过远
+fn is_in_front(pose: &SE3, point1: &Vector2<f64>, point2: &Vector2<f64>) -> bool {
+    let Some(point) = triangulate(pose, point1, point2) else {
+        return false;
+    };
+    let depth2 = pose.transform_point(&point).z;
+    let max_depth = MAX_DEPTH_RATIO * pose.translation().norm();
+    point.z > 0.0 && depth2 > 0.0 && point.z < max_depth && depth2 < max_depth
+}
+
+/// 线性三角化，第一帧位姿为单位阵，第二帧为T21，返回第一帧坐标系下的点
+pub fn triangulate(pose: &SE3, point1: &Vector2<f64>, point2: &Vector2<f64>) -> Option<Vector3<f64>> {
+    let projection = pose.matrix();
+    let mut a = Matrix4::zeros();
+    a.set_row(0, &RowVector4::new(-1.0, 0.0, point1.x, 0.0));
+    a.set_row(1, &RowVector4::new(0.0, -1.0, point1.y, 0.0));
+    a.set_row(2, &(projection.row(2) * point2.x - projection.row(0)));
+    a.set_row(3, &(projection.row(2) * point2.y - projection.row(1)));
+    let v_t = a.svd(false, true).v_t?;
+    let homogeneous = v_t.row(3);
+    if homogeneous[3].abs() < 1e-12 {
+        return None;
+    }
+    Some(Vector3::new(homogeneous[0], homogeneous[1], homogeneous[2]) / homogeneous[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+    use vslam_core::camera::PinholeCamera;
+
+    /// 随机场景：两帧都能看到的点，以及真实的T21
+    fn scene(count: usize, seed: u64) -> (Vec<Vector3<f64>>, SE3) {
+        let mut state = seed;
+        let mut uniform = |low: f64, high: f64| low + (high - low) * (split_mix64(&mut state) % 100000) as f64 / 100000.0;
+        let points = (0..count)
+            .map(|_| Vector3::new(uniform(-2.0, 2.0), uniform(-1.5, 1.5), uniform(4.0, 10.0)))
+            .collect();
+        let pose = SE3::new(UnitQuaternion::from_euler_angles(0.05, -0.1, 0.03), Vector3::new(0.6, 0.1, -0.2));
+        (points, pose)
+    }
+
+    fn normalized(point: &Vector3<f64>) -> Vector2<f64> {
+        Vector2::new(point.x / point.z, point.y / point.z)
+    }
+
+    #[test]
+    fn five_point_contains_true_essential() {
+        let (points, pose) = scene(5, 1);
+        let points1: Vec<Vector2<f64>> = points.iter().map(normalized).collect();
+        let points2: Vec<Vector2<f64>> = points.iter().map(|p| normalized(&pose.transform_point(p))).collect();
+        let t = pose.translation().normalize();
+        let truth = (Matrix3::new(0.0, -t.z, t.y, t.z, 0.0, -t.x, -t.y, t.x, 0.0) * pose.rotation_matrix()).normalize();
+
+        let solutions = five_point(&points1, &points2);
+        assert!(!solutions.is_empty() && solutions.len() <= 10);
+        let best = solutions
+            .iter()
+            .map(|e| (e - truth).norm().min((e + truth).norm()))
+            .fold(f64::INFINITY, f64::min);
+        assert!(best < 1e-6, "{}", best);
+        for essential in &solutions {
+            for i in 0..5 {
+                assert!(sampson_error(essential, &points1[i], &points2[i]) < 1e-16);
+            }
+        }
+        assert!(five_point(&points1[..4], &points2[..4]).is_empty());
+    }
+
+    #[test]
+    fn ransac_recovers_pose_with_outliers() {
+        let camera = PinholeCamera::new(450.0, 450.0, 320.0, 240.0, 640, 480);
+        let (points, pose) = scene(200, 2);
+        let mut state = 3;
+        let mut noise = || ((split_mix64(&mut state) % 1000) as f64 / 1000.0 - 0.5) * 0.6;
+        let mut keypoints1 = Vec::new();
+        let mut keypoints2 = Vec::new();
+        for (i, point) in points.iter().enumerate() {
+            let pixel1 = camera.project(point).unwrap();
+            let mut pixel2 = camera.project(&pose.transform_point(point)).unwrap();
+            // 每4个点中1个为外点
+            if i % 4 == 0 {
+                pixel2 += Vector2::new(30.0 + i as f64 % 17.0, -25.0);
+            }
+            keypoints1.push(KeyPoint::new(pixel1 + Vector2::new(noise(), noise())));
+            keypoints2.push(KeyPoint::new(pixel2 + Vector2::new(noise(), noise())));
+        }
+        let matches: Vec<(usize, usize)> = (0..points.len()).map(|i| (i, i)).collect();
+
+        let result = EssentialEstimator::default().estimate(&camera, &keypoints1, &keypoints2, &matches).unwrap();
+        let rotation_error = (result.pose.rotation().inverse() * pose.rotation()).angle();
+        assert!(rotation_error < 0.01, "{}", rotation_error);
+        let translation_error = result.pose.translation().normalize().dot(&pose.translation().normalize());
+        assert!(translation_error > 0.999, "{}", translation_error);
+        assert_eq!(result.inliers.len(), matches.len());
+        for (i, &inlier) in result.inliers.iter().enumerate() {
+            if i % 4 == 0 {
+                assert!(!inlier, "{}", i);
+            }
+        }
+        assert!(result.inliers.iter().filter(|&&inlier| inlier).count() >= 140);
+    }
+}
@@ -0,0 +1,427 @@
+use image::GrayImage;
+use nalgebra::Vector2;
+use vslam_core::feature::{Detector, KeyPoint};
+
+/// Bresenham圆周上16个像素的偏移
+const CIRCLE: [(i32, i32); 16] = [(0, 3),(1, 3),        (2, 2),        (3, 1),        (3, 0),        (3, -1),        (2, -2),        (1, -3),        (0, -3),        (-1, -3),        (-2, -2),        (-3, -1),        (-3, 0),        (-3, 1),        (-2, 2),        (-1, 3),    ];
+/// 半径2的圆周上12个像素的偏移
+const CIRCLE_12: [(i32, i32); 12] = [(0, 2), (1, 2), (2, 1), (2, 0), (2, -1), (1, -2), (0, -2), (-1, -2), (-2, -1), (-2, 0), (-2, 1), (-1, 2)];
+/// 半径1的圆周上8个像素的偏移
+const CIRCLE_8: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+
+/// FAST变体，FAST-N要求圆周上连续N个像素都比中心亮或暗
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FastType {
+    Fast5,  // 8像素圆周
+    Fast7,  // 12像素圆周
+    #[default]
+    Fast9,  // 16像素圆周
+    Fast12, // 16像素圆周
+}
+
+impl FastType {
+    /// 圆周像素偏移
+    fn circle(&self) -> &'static [(i32, i32)] {
+        match self {
+            FastType::Fast5 => &CIRCLE_8,
+            FastType::Fast7 => &CIRCLE_12,
+            FastType::Fast9 | FastType::Fast12 => &CIRCLE,
+        }
+    }
+
+    /// 连续像素数N
+    fn arc_length(&self) -> usize {
+        match self {
+            FastType::Fast5 => 5,
+            FastType::Fast7 => 7,
+            FastType::Fast9 => 9,
+            FastType::Fast12 => 12,
+        }
+    }
+
+    /// 圆周半径，也是检测时忽略的边界宽度
+    fn radius(&self) -> u32 {
+        match self {
+            FastType::Fast5 => 1,
+            FastType::Fast7 => 2,
+            FastType::Fast9 | FastType::Fast12 => 3,
+        }
+    }
+}
+
+/// 自适应阈值：把影像划分为网格，角点过少的格子逐步降低阈值重新检测
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveThreshold {
+    pub cell_size: u32,     // 格子边长
+    pub min_corners: usize, // 每个格子期望的最少角点数
+    pub min_threshold: u8,  // 阈值下限
+}
+
+impl Default for AdaptiveThreshold {
+    fn default() -> Self {
+        AdaptiveThreshold { cell_size: 30, min_corners: 1, min_threshold: 7 }
+    }
+}
+
+/// FAST角点检测器
+#[derive(Clone, Copy, Debug)]
+pub struct FAST {
+    pub threshold: u8,                        // 中心与圆周像素的灰度差阈值
+    pub nonmax_suppression: bool,             // 是否在3x3邻域内做非极大值抑制
+    pub fast_type: FastType,                  // FAST变体
+    pub adaptive: Option<AdaptiveThreshold>,  // 自适应阈值，None时整幅影像使用同一阈值
+}
+
+impl Default for FAST {
+    fn default() -> Self {
+        FAST { threshold: 20, nonmax_suppression: true, fast_type: FastType::Fast9, adaptive: None }
+    }
+}
+
+impl Detector for FAST {
+    fn detect(&self, image: &GrayImage) -> Vec<KeyPoint> {
+        let border = self.fast_type.radius();
+        let width = image.width();
+        let height = image.height();
+        if width <= 2 * border || height <= 2 * border {
+            return Vec::new();
+        }
+
+        let keypoints = match self.adaptive {
+            None => fast(image, self.threshold, self.threshold, self.fast_type, (border, border, width - border, height - border)),
+            Some(adaptive) => {
+                let cell_size = adaptive.cell_size.max(1);
+                let mut keypoints = Vec::new();
+                for y0 in (border..height - border).step_by(cell_size as usize) {
+                    for x0 in (border..width - border).step_by(cell_size as usize) {
+                        let cell = (x0, y0, (x0 + cell_size).min(width - border), (y0 + cell_size).min(height - border));
+                        let mut threshold = self.threshold;
+                        let mut cell_keypoints = fast(image, threshold, self.threshold, self.fast_type, cell);
+                        // 角点过少时阈值减半，直到下限；响应仍按原阈值计算，各格子之间可比
+                        while cell_keypoints.len() < adaptive.min_corners && threshold > adaptive.min_threshold {
+                            threshold = (threshold / 2).max(adaptive.min_threshold);
+                            cell_keypoints = fast(image, threshold, self.threshold, self.fast_type, cell);
+                        }
+                        keypoints.extend(cell_keypoints);
+                    }
+                }
+                keypoints
+            }
+        };
+
+        if self.nonmax_suppression {
+            nonmax_suppression(keypoints, width, height)
+        } else {
+            keypoints
+        }
+    }
+}
+
+/// fast角点检测
+/// 只检测region = (x0, y0, x1, y1)范围内的像素，region须与影像边界保持圆周半径的距离
+/// threshold用于判断角点，score_threshold用于计算response
+fn fast(image: &GrayImage, threshold: u8, score_threshold: u8, fast_type: FastType, region: (u32, u32, u32, u32)) -> Vec<KeyPoint> {
+    let (x0, y0, x1, y1) = region;
+    let mut keypoints:Vec<KeyPoint>=Vec::new();
+
+    // 通过is_corner_fast检测的，加入keypoints中
+    for y in y0..y1{
+        for x in x0..x1{
+            let pixel_value=image.get_pixel(x,y).0[0];
+            if is_corner_fast(image, x, y, pixel_value, threshold, fast_type) {
+                keypoints.push(KeyPoint {
+                    location: Vector2::new(x as f64, y as f64),
+                    size: (2 * fast_type.radius() + 1) as f64,
+                    angle: 0.0,
+                    response: corner_score(image, x, y, pixel_value, score_threshold, fast_type),
+                    octave: 0,
+                });
+            }
+        }
+    }
+
+    keypoints
+}
+
+/// 非极大值抑制，只保留3x3邻域内response最大的角点
+/// response相等时保留光栅顺序靠前的
+fn nonmax_suppression(keypoints: Vec<KeyPoint>, width: u32, height: u32) -> Vec<KeyPoint> {
+    // 每个像素上角点的(response, 序号)
+    let mut scores: Vec<Option<(f64, usize)>> = vec![None; (width * height) as usize];
+    for (index, keypoint) in keypoints.iter().enumerate() {
+        let x = keypoint.location.x as u32;
+        let y = keypoint.location.y as u32;
+        scores[(y * width + x) as usize] = Some((keypoint.response, index));
+    }
+
+    keypoints
+        .iter()
+        .enumerate()
+        .filter(|&(index, keypoint)| {
+            let x = keypoint.location.x as i32;
+            let y = keypoint.location.y as i32;
+            let beaten = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy))).any(|(u, v)| {
+                if u < 0 || v < 0 || u >= width as i32 || v >= height as i32 {
+                    return false;
+                }
+                match scores[(v as u32 * width + u as u32) as usize] {
+                    Some((score, other)) => score > keypoint.response || (score == keypoint.response && other < index),
+                    None => false,
+                }
+            });
+            !beaten
+        })
+        .map(|(_, keypoint)| *keypoint)
+        .collect()
+}
+
+/// 四叉树节点，覆盖[min, max)区域
+struct QuadtreeNode {
+    min: Vector2<f64>,
+    max: Vector2<f64>,
+    keypoints: Vec<KeyPoint>,
+}
+
+impl QuadtreeNode {
+    /// 可以继续划分：多于一个特征点且区域不小于一个像素
+    fn is_splittable(&self) -> bool {
+        self.keypoints.len() > 1 && (self.max - self.min).min() >= 1.0
+    }
+
+    /// 划分为四个子节点，丢弃空节点
+    fn split(self) -> Vec<QuadtreeNode> {
+        let center = (self.min + self.max) / 2.0;
+        let mut children: Vec<QuadtreeNode> = [
+            (self.min, center),
+            (Vector2::new(center.x, self.min.y), Vector2::new(self.max.x, center.y)),
+            (Vector2::new(self.min.x, center.y), Vector2::new(center.x, self.max.y)),
+            (center, self.max),
+        ]
+        .into_iter()
+        .map(|(min, max)| QuadtreeNode { min, max, keypoints: Vec::new() })
+        .collect();
+        for keypoint in self.keypoints {
+            let right = keypoint.location.x >= center.x;
+            let bottom = keypoint.location.y >= center.y;
+            children[right as usize + 2 * bottom as usize].keypoints.push(keypoint);
+        }
+        children.retain(|child| !child.keypoints.is_empty());
+        children
+    }
+}
+
+/// 四叉树均匀化特征点分布，与ORB-SLAM的DistributeOctTree相同
+/// 区域[min, max)先按宽高比划分为若干正方形节点，再反复把特征点最多的节点一分为四，
+/// 直到节点数达到target或无法继续划分，每个节点只保留response最大的特征点
+pub fn distribute_quadtree(keypoints: Vec<KeyPoint>, min: Vector2<f64>, max: Vector2<f64>, target: usize) -> Vec<KeyPoint> {
+    if keypoints.is_empty() || target == 0 {
+        return Vec::new();
+    }
+
+    let size = max - min;
+    let n_initial = ((size.x / size.y).round() as usize).max(1);
+    let node_width = size.x / n_initial as f64;
+    let mut nodes: Vec<QuadtreeNode> = (0..n_initial)
+        .map(|i| QuadtreeNode {
+            min: Vector2::new(min.x + node_width * i as f64, min.y),
+            max: Vector2::new(min.x + node_width * (i + 1) as f64, max.y),
+            keypoints: Vec::new(),
+        })
+        .collect();
+    for keypoint in keypoints {
+        let i = (((keypoint.location.x - min.x) / node_width) as usize).min(n_initial - 1);
+        nodes[i].keypoints.push(keypoint);
+    }
+    nodes.retain(|node| !node.keypoints.is_empty());
+
+    while nodes.len() < target {
+        // 特征点多的节点先划分
+        if !nodes.iter().any(QuadtreeNode::is_splittable) {
+            break;
+        }
+        nodes.sort_by_key(|node| std::cmp::Reverse(node.keypoints.len()));
+        let mut count = nodes.len();
+        let mut next = Vec::with_capacity(nodes.len() * 4);
+        for node in nodes {
+            if count < target && node.is_splittable() {
+                let children = node.split();
+                count += children.len() - 1;
+                next.extend(children);
+            } else {
+                next.push(node);
+            }
+        }
+        nodes = next;
+    }
+
+    let mut result: Vec<KeyPoint> = nodes
+        .into_iter()
+        .filter_map(|node| node.keypoints.into_iter().max_by(|a, b| a.response.total_cmp(&b.response)))
+        .collect();
+    if result.len() > target {
+        result.sort_by(|a, b| b.response.total_cmp(&a.response));
+        result.truncate(target);
+    }
+    result
+}
+
+/// 判断输入是否是fast角点
+fn is_corner_fast(image: &GrayImage, x: u32, y: u32, pixel_value: u8, threshold: u8, fast_type: FastType) -> bool {
+    let darker = |value| (value < pixel_value) && ( pixel_value -value > threshold);
+    let brighter = |value| (value > pixel_value) && (value - pixel_value > threshold);
+    let circle = fast_type.circle();
+    let arc_length = fast_type.arc_length();
+
+    // 高速测试：连续arc_length个像素至少覆盖四个等间隔像素中的arc_length / (圆周长 / 4)个
+    let quarter = circle.len() / 4;
+    let required = arc_length / quarter;
+    let (mut num_darker, mut num_brighter) = (0, 0);
+    for &(dx, dy) in circle.iter().step_by(quarter) {
+        let pixel = image.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32).0[0];
+        num_darker += darker(pixel) as usize;
+        num_brighter += brighter(pixel) as usize;
+    }
+    if num_darker < required && num_brighter < required {
+        return false;
+    }
+
+    let darker_brighter:Vec<(bool,bool)>= circle
+        .iter()
+        .map(|&(dx, dy)| {
+            let pixel = image.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32).0[0];
+            (darker(pixel), brighter(pixel))
+        })
+        .collect::<Vec<_>>();
+
+    let consecutive_darker_or_brighter = |count: usize| -> bool {
+        let mut consecutive_darker = 0;
+        let mut consecutive_brighter = 0;
+        for &(darker, brighter) in darker_brighter.iter().cycle().take(darker_brighter.len() * 2) {
+            if darker {
+                consecutive_darker += 1;
+                consecutive_brighter = 0;
+            } else if brighter {
+                consecutive_brighter += 1;
+                consecutive_darker = 0;
+            } else {
+                consecutive_darker = 0;
+                consecutive_brighter = 0;
+            }
+            if consecutive_darker >= count || consecutive_brighter >= count {
+                return true;
+            }
+        }
+        false
+    };
+
+
+    consecutive_darker_or_brighter(arc_length) 
+}
+
+/// 角点响应：圆周上超过阈值的像素与中心灰度差的绝对值之和
+fn corner_score(image: &GrayImage, x: u32, y: u32, pixel_value: u8, threshold: u8, fast_type: FastType) -> f64 {
+    let mut brighter = 0.0;
+    let mut darker = 0.0;
+    for &(dx, dy) in fast_type.circle().iter() {
+        let pixel = image.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32).0[0];
+        let difference = pixel as f64 - pixel_value as f64;
+        if difference > threshold as f64 {
+            brighter += difference - threshold as f64;
+        } else if -difference > threshold as f64 {
+            darker += -difference - threshold as f64;
+        }
+    }
+    f64::max(brighter, darker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonmax_suppression_keeps_isolated_corners() {
+        // 暗背景上的亮方块，四个角各应只有一个角点
+        let image = GrayImage::from_fn(40, 40, |x, y| image::Luma([if (10..30).contains(&x) && (10..30).contains(&y) { 200 } else { 20 }]));
+        let keypoints = FAST::default().detect(&image);
+        assert!(!keypoints.is_empty());
+        for (i, a) in keypoints.iter().enumerate() {
+            for b in &keypoints[i + 1..] {
+                assert!((a.location - b.location).amax() > 1.0);
+            }
+        }
+        let all = FAST { nonmax_suppression: false, ..FAST::default() }.detect(&image);
+        assert!(all.len() > keypoints.len());
+    }
+
+    #[test]
+    fn quadtree_spreads_keypoints() {
+        // 左上角密集的一簇，其余区域稀疏
+        let mut keypoints: Vec<KeyPoint> = (0..400)
+            .map(|i| KeyPoint { response: 100.0 + i as f64, ..KeyPoint::new(Vector2::new((i % 20) as f64, (i / 20) as f64)) })
+            .collect();
+        for i in 0..16 {
+            let location = Vector2::new(50.0 + 40.0 * (i % 4) as f64, 50.0 + 40.0 * (i / 4) as f64);
+            keypoints.push(KeyPoint { response: 1.0, ..KeyPoint::new(location) });
+        }
+        let selected = distribute_quadtree(keypoints, Vector2::zeros(), Vector2::new(200.0, 200.0), 32);
+        assert!(selected.len() <= 32);
+        let sparse = selected.iter().filter(|keypoint| keypoint.response == 1.0).count();
+        assert_eq!(sparse, 16);
+    }
+
+    #[test]
+    fn variants_detect_corners() {
+        // 亮方块和一个孤立亮点
+        let image = GrayImage::from_fn(40, 40, |x, y| {
+            let inside = (10..30).contains(&x) && (10..30).contains(&y) || (x, y) == (35, 35);
+            image::Luma([if inside { 200 } else { 20 }])
+        });
+        for fast_type in [FastType::Fast5, FastType::Fast7, FastType::Fast9, FastType::Fast12] {
+            let keypoints = FAST { fast_type, ..FAST::default() }.detect(&image);
+            let found = |x: f64, y: f64| keypoints.iter().any(|keypoint| (keypoint.location - Vector2::new(x, y)).amax() <= 2.0);
+            assert!(found(35.0, 35.0), "{:?}", fast_type);
+            // 直角的圆周上最多11个像素比中心暗，FAST-12检测不到
+            let corners = [(10.0, 10.0), (29.0, 10.0), (10.0, 29.0), (29.0, 29.0)];
+            assert_eq!(corners.iter().all(|&(x, y)| found(x, y)), fast_type != FastType::Fast12, "{:?}", fast_type);
+        }
+    }
+
+    #[test]
+    fn adaptive_threshold_finds_corners_in_dark_regions() {
+        // 左半边对比度正常，右半边很暗
+        let image = GrayImage::from_fn(80, 40, |x, y| {
+            let inside = (x % 40 >= 10) && (x % 40 < 30) && (10..30).contains(&y);
+            let (low, high) = if x < 40 { (20, 200) } else { (5, 15) };
+            image::Luma([if inside { high } else { low }])
+        });
+        let fixed = FAST::default().detect(&image);
+        assert!(fixed.iter().all(|keypoint| keypoint.location.x < 40.0));
+        let adaptive = FAST { adaptive: Some(AdaptiveThreshold { cell_size: 20, min_corners: 1, min_threshold: 5 }), ..FAST::default() }.detect(&image);
+        assert!(adaptive.iter().any(|keypoint| keypoint.location.x >= 40.0));
+    }
+
+    #[test]
+    fn adaptive_threshold_keeps_scores_comparable() {
+        // 右半边的角点只有降低阈值后才能检出
+        let image = GrayImage::from_fn(80, 40, |x, y| {
+            let inside = (x % 40 >= 10) && (x % 40 < 30) && (10..30).contains(&y);
+            let (low, high) = if x < 40 { (20, 200) } else { (5, 15) };
+            image::Luma([if inside { high } else { low }])
+        });
+        let fixed = FAST::default().detect(&image);
+        let adaptive = FAST { adaptive: Some(AdaptiveThreshold { cell_size: 20, min_corners: 1, min_threshold: 5 }), ..FAST::default() }.detect(&image);
+        // 两种模式下左半边角点的响应相同
+        for keypoint in &fixed {
+            let same = adaptive.iter().find(|other| other.location == keypoint.location).unwrap();
+            assert_eq!(same.response, keypoint.response);
+        }
+        // 右半边降低阈值检出的角点，响应仍按原阈值计算
+        let dark: Vec<&KeyPoint> = adaptive.iter().filter(|keypoint| keypoint.location.x >= 40.0).collect();
+        assert!(!dark.is_empty());
+        for keypoint in dark {
+            let (x, y) = (keypoint.location.x as u32, keypoint.location.y as u32);
+            let expected = corner_score(&image, x, y, image.get_pixel(x, y).0[0], 20, FastType::Fast9);
+            assert_eq!(keypoint.response, expected);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use image::GrayImage;
+use nalgebra::Vector2;
+use vslam_core::feature::{Detector, KeyPoint};
+
+/// Good Features To Track：Shi-Tomasi最小特征值或Harris响应角点，用于光流跟踪
+#[derive(Clone, Debug)]
+pub struct GFTT {
+    pub max_corners: usize,     // 角点数上限，0为不限
+    pub quality_level: f64,     // 响应低于最大响应的该比例的角点被舍弃
+    pub min_distance: f64,      // 角点之间的最小距离
+    pub block_size: u32,        // 结构张量的统计窗口边长
+    pub use_harris: bool,       // 使用Harris响应，否则为最小特征值
+    pub harris_k: f64,          // Harris响应的经验系数
+    pub mask: Option<GrayImage>, // 只在非零像素处检测，超出掩膜范围的像素视为被遮挡
+}
+
+impl Default for GFTT {
+    fn default() -> Self {
+        GFTT {
+            max_corners: 1000,
+            quality_level: 0.01,
+            min_distance: 10.0,
+            block_size: 3,
+            use_harris: false,
+            harris_k: 0.04,
+            mask: None,
+        }
+    }
+}
+
+impl Detector for GFTT {
+    /// 按响应从大到小返回角点
+    fn detect(&self, image: &GrayImage) -> Vec<KeyPoint> {
+        let width = image.width() as i32;
+        let height = image.height() as i32;
+        let responses = self.compute_responses(image);
+        let max_response = responses.iter().cloned().fold(0.0, f64::max);
+        if max_response <= 0.0 {
+            return Vec::new();
+        }
+        let threshold = self.quality_level * max_response;
+        let response = |x: i32, y: i32| responses[(y * width + x) as usize];
+
+        // 阈值和3x3非极大值抑制
+        let mut candidates = Vec::new();
+        for y in 1..(height - 1) {
+            for x in 1..(width - 1) {
+                let value = response(x, y);
+                if value <= threshold || !self.is_unmasked(x as u32, y as u32) {
+                    continue;
+                }
+                let is_maximum = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .all(|(dx, dy)| response(x + dx, y + dy) <= value);
+                if is_maximum {
+                    candidates.push((value, x, y));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // 按响应从大到小贪心选取，网格加速最小距离检查
+        let cell_size = self.min_distance.max(1.0);
+        let grid_width = (width as f64 / cell_size).ceil() as i32;
+        let grid_height = (height as f64 / cell_size).ceil() as i32;
+        let mut grid: Vec<Vec<Vector2<f64>>> = vec![Vec::new(); (grid_width * grid_height) as usize];
+        let mut keypoints = Vec::new();
+        for (value, x, y) in candidates {
+            let location = Vector2::new(x as f64, y as f64);
+            let cell_x = (location.x / cell_size) as i32;
+            let cell_y = (location.y / cell_size) as i32;
+            let too_close = (cell_y - 1..=cell_y + 1)
+                .flat_map(|j| (cell_x - 1..=cell_x + 1).map(move |i| (i, j)))
+                .filter(|&(i, j)| i >= 0 && j >= 0 && i < grid_width && j < grid_height)
+                .any(|(i, j)| {
+                    grid[(j * grid_width + i) as usize]
+                        .iter()
+                        .any(|other| (other - location).norm() < self.min_distance)
+                });
+            if too_close {
+                continue;
+            }
+
+            grid[(cell_y * grid_width + cell_x) as usize].push(location);
+            keypoints.push(KeyPoint {
+                location,
+                size: self.block_size as f64,
+                angle: 0.0,
+                response: value,
+                octave: 0,
+            });
+            if self.max_corners > 0 && keypoints.len() >= self.max_corners {
+                break;
+            }
+        }
+
+        keypoints
+    }
+}
+
+impl GFTT {
+    fn is_unmasked(&self, x: u32, y: u32) -> bool {
+        self.mask
+            .as_ref()
+            .is_none_or(|mask| mask.get_pixel_checked(x, y).is_some_and(|pixel| pixel.0[0] != 0))
+    }
+
+    /// 每个像素的角点响应，行优先
+    /// Shi-Tomasi响应为结构张量的最小特征值，Harris响应为det(M) - k·tr(M)²
+    fn compute_responses(&self, image: &GrayImage) -> Vec<f64> {
+        let width = image.width() as i32;
+        let height = image.height() as i32;
+        let mut responses = vec![0.0; (width * height) as usize];
+        if width < 3 || height < 3 {
+            return responses;
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let tensor = structure_tensor(image, x, y, self.block_size);
+                responses[(y * width + x) as usize] = if self.use_harris {
+                    harris_response(tensor, self.harris_k)
+                } else {
+                    let [a, b, c] = tensor;
+                    let half_trace = (a + c) / 2.0;
+                    half_trace - (((a - c) / 2.0).powi(2) + b * b).sqrt()
+                };
+            }
+        }
+
+        responses
+    }
+}
+
+/// 以(x, y)为中心block_size窗口内的结构张量M = Σ[Ix², IxIy; IxIy, Iy²]，返回[Ix², IxIy, Iy²]
+/// 梯度为Sobel算子，影像边界像素的梯度为0，窗口超出影像的部分不参与统计
+pub(crate) fn structure_tensor(image: &GrayImage, x: i32, y: i32, block_size: u32) -> [f64; 3] {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+    let pixel = |u: i32, v: i32| image.get_pixel(u as u32, v as u32).0[0] as f64;
+    let radius = (block_size / 2) as i32;
+    let mut sum = [0.0; 3];
+    for v in (y - radius).max(1)..=(y + radius).min(height - 2) {
+        for u in (x - radius).max(1)..=(x + radius).min(width - 2) {
+            let ix = (pixel(u + 1, v - 1) + 2.0 * pixel(u + 1, v) + pixel(u + 1, v + 1))
+                - (pixel(u - 1, v - 1) + 2.0 * pixel(u - 1, v) + pixel(u - 1, v + 1));
+            let iy = (pixel(u - 1, v + 1) + 2.0 * pixel(u, v + 1) + pixel(u + 1, v + 1))
+                - (pixel(u - 1, v - 1) + 2.0 * pixel(u, v - 1) + pixel(u + 1, v - 1));
+            sum[0] += ix * ix;
+            sum[1] += ix * iy;
+            sum[2] += iy * iy;
+        }
+    }
+    // 归一化到与窗口大小和灰度范围无关
+    let scale = 1.0 / (4.0 * 255.0 * block_size.max(1) as f64);
+    let scale2 = scale * scale;
+    sum.map(|value| value * scale2)
+}
+
+/// Harris角点响应det(M) - k·tr(M)²
+pub(crate) fn harris_response(tensor: [f64; 3], k: f64) -> f64 {
+    let [a, b, c] = tensor;
+    a * c - b * b - k * (a + c) * (a + c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 两个亮方块，共8个角
+    fn squares_image() -> GrayImage {
+        GrayImage::from_fn(100, 60, |x, y| {
+            let inside = (10..30).contains(&y) && ((10..30).contains(&x) || (60..80).contains(&x));
+            image::Luma([if inside { 200 } else { 20 }])
+        })
+    }
+
+    #[test]
+    fn detects_square_corners() {
+        let image = squares_image();
+        for use_harris in [false, true] {
+            let keypoints = GFTT { use_harris, ..GFTT::default() }.detect(&image);
+            assert_eq!(keypoints.len(), 8, "{}", use_harris);
+            for (i, a) in keypoints.iter().enumerate() {
+                for b in &keypoints[i + 1..] {
+                    assert!((a.location - b.location).norm() >= 10.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn respects_mask_and_max_corners() {
+        let image = squares_image();
+        let mask = GrayImage::from_fn(100, 60, |x, _| image::Luma([if x < 50 { 255 } else { 0 }]));
+        let keypoints = GFTT { mask: Some(mask), ..GFTT::default() }.detect(&image);
+        assert_eq!(keypoints.len(), 4);
+        assert!(keypoints.iter().all(|keypoint| keypoint.location.x < 50.0));
+
+        // 比影像小的掩膜
+        let mask = GrayImage::from_pixel(50, 40, image::Luma([255]));
+        let keypoints = GFTT { mask: Some(mask), ..GFTT::default() }.detect(&image);
+        assert_eq!(keypoints.len(), 4);
+
+        let keypoints = GFTT { max_corners: 3, ..GFTT::default() }.detect(&image);
+        assert_eq!(keypoints.len(), 3);
+    }
+}
@@ -0,0 +1,180 @@
+/// 汉明距离的计算实现
+/// 描述子以&[[u64; N]]连续存放，内层循环对整组train描述子计算，避免逐对分派
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HammingKernel {
+    Scalar, // 可移植实现
+    Popcnt, // 硬件popcnt指令
+    Avx2,   // AVX2查表计数，每次处理256位
+}
+
+impl HammingKernel {
+    /// 当前CPU支持的最快实现
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return HammingKernel::Avx2;
+            }
+            if is_x86_feature_detected!("popcnt") {
+                return HammingKernel::Popcnt;
+            }
+        }
+        HammingKernel::Scalar
+    }
+
+    /// 当前CPU是否支持该实现
+    pub fn is_supported(&self) -> bool {
+        match self {
+            HammingKernel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            HammingKernel::Popcnt => is_x86_feature_detected!("popcnt"),
+            #[cfg(target_arch = "x86_64")]
+            HammingKernel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// 在train中查找与query汉明距离最小的两个
+    /// 不支持的实现退化为Scalar
+    pub fn nearest_two<const N: usize>(&self, query: &[u64; N], train: &[[u64; N]]) -> Nearest {
+        match self {
+            // SAFETY: 已检查CPU支持对应指令集
+            #[cfg(target_arch = "x86_64")]
+            HammingKernel::Popcnt if self.is_supported() => unsafe { nearest_two_popcnt(query, train) },
+            #[cfg(target_arch = "x86_64")]
+            HammingKernel::Avx2 if self.is_supported() => unsafe { nearest_two_avx2(query, train) },
+            _ => nearest_two_scalar(query, train),
+        }
+    }
+}
+
+/// 最近邻和次近邻
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Nearest {
+    pub index: Option<usize>, // 最近邻索引，train为空时为None
+    pub best: u32,            // 最小距离
+    pub second: u32,          // 次小距离，不存在时为u32::MAX
+}
+
+impl Nearest {
+    const EMPTY: Nearest = Nearest { index: None, best: u32::MAX, second: u32::MAX };
+
+    #[inline(always)]
+    fn update(&mut self, index: usize, distance: u32) {
+        if distance < self.best {
+            self.second = self.best;
+            self.best = distance;
+            self.index = Some(index);
+        } else if distance < self.second {
+            self.second = distance;
+        }
+    }
+}
+
+/// 两个描述子的汉明距离，可移植实现
+#[inline(always)]
+pub fn hamming_distance<const N: usize>(a: &[u64; N], b: &[u64; N]) -> u32 {
+    let mut distance = 0;
+    for i in 0..N {
+        distance += (a[i] ^ b[i]).count_ones();
+    }
+    distance
+}
+
+#[inline(always)]
+fn nearest_two_inline<const N: usize>(query: &[u64; N], train: &[[u64; N]]) -> Nearest {
+    let mut nearest = Nearest::EMPTY;
+    for (j, descriptor) in train.iter().enumerate() {
+        nearest.update(j, hamming_distance(query, descriptor));
+    }
+    nearest
+}
+
+fn nearest_two_scalar<const N: usize>(query: &[u64; N], train: &[[u64; N]]) -> Nearest {
+    nearest_two_inline(query, train)
+}
+
+/// 与Scalar相同的代码，开启popcnt后count_ones编译为单条指令
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "popcnt")]
+unsafe fn nearest_two_popcnt<const N: usize>(query: &[u64; N], train: &[[u64; N]]) -> Nearest {
+    nearest_two_inline(query, train)
+}
+
+/// AVX2实现：异或后按4位查表计数，再用sad指令横向求和
+/// N不是4的倍数时剩余部分用popcnt
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,popcnt")]
+unsafe fn nearest_two_avx2<const N: usize>(query: &[u64; N], train: &[[u64; N]]) -> Nearest {
+    use std::arch::x86_64::*;
+
+    let chunks = N / 4;
+    let lookup = _mm256_setr_epi8(
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+        0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+    );
+    let low_mask = _mm256_set1_epi8(0x0f);
+    let zero = _mm256_setzero_si256();
+    let mut nearest = Nearest::EMPTY;
+
+    for (j, descriptor) in train.iter().enumerate() {
+        let mut sums = zero;
+        for chunk in 0..chunks {
+            // SAFETY: chunk * 4 + 3 < N，读取的256位都在数组内
+            let a = _mm256_loadu_si256(query.as_ptr().add(chunk * 4) as *const __m256i);
+            let b = _mm256_loadu_si256(descriptor.as_ptr().add(chunk * 4) as *const __m256i);
+            let bits = _mm256_xor_si256(a, b);
+            let low = _mm256_and_si256(bits, low_mask);
+            let high = _mm256_and_si256(_mm256_srli_epi16(bits, 4), low_mask);
+            let counts = _mm256_add_epi8(_mm256_shuffle_epi8(lookup, low), _mm256_shuffle_epi8(lookup, high));
+            sums = _mm256_add_epi64(sums, _mm256_sad_epu8(counts, zero));
+        }
+        let mut distance = (_mm256_extract_epi64(sums, 0)
+            + _mm256_extract_epi64(sums, 1)
+            + _mm256_extract_epi64(sums, 2)
+            + _mm256_extract_epi64(sums, 3)) as u32;
+        for i in chunks * 4..N {
+            distance += (query[i] ^ descriptor[i]).count_ones();
+        }
+        nearest.update(j, distance);
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+
+    fn descriptors<const N: usize>(count: usize, seed: u64) -> Vec<[u64; N]> {
+        let mut state = seed;
+        (0..count).map(|_| [0; N].map(|_: u64| split_mix64(&mut state))).collect()
+    }
+
+    fn check_kernels<const N: usize>() {
+        let query = descriptors::<N>(20, 1);
+        let train = descriptors::<N>(50, 2);
+        for kernel in [HammingKernel::Scalar, HammingKernel::Popcnt, HammingKernel::Avx2] {
+            for q in &query {
+                let expected = train
+                    .iter()
+                    .enumerate()
+                    .map(|(j, t)| (hamming_distance(q, t), j))
+                    .min()
+                    .unwrap();
+                let nearest = kernel.nearest_two(q, &train);
+                assert_eq!((nearest.best, nearest.index), (expected.0, Some(expected.1)), "{:?}", kernel);
+                assert!(nearest.second >= nearest.best);
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_agree_with_scalar() {
+        check_kernels::<4>();
+        check_kernels::<2>();
+        check_kernels::<5>();
+        assert_eq!(HammingKernel::detect().nearest_two(&[0u64; 4], &[]), Nearest::EMPTY);
+    }
+}
@@ -0,0 +1,273 @@
+use image::{GrayImage, ImageBuffer, Luma};
+use nalgebra::{Matrix2, Vector2};
+use vslam_core::feature::KeyPoint;
+
+/// 单通道浮点影像
+type FloatImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// 金字塔顶层影像的最小边长
+const MIN_LEVEL_SIZE: u32 = 16;
+
+/// 金字塔Lucas-Kanade光流跟踪器
+#[derive(Clone, Copy, Debug)]
+pub struct KLT {
+    pub window_size: u32,                // 跟踪窗口边长，奇数
+    pub levels: usize,                   // 金字塔层数
+    pub max_iterations: usize,           // 每层迭代次数上限
+    pub epsilon: f64,                    // 更新量小于该值时停止迭代，单位像素
+    pub min_eigen_threshold: f64,        // 窗口梯度矩阵的最小特征值阈值，低于该值认为无法跟踪
+    pub max_forward_backward_error: f64, // 正反向跟踪的最大距离，非正数时不检查
+}
+
+impl Default for KLT {
+    fn default() -> Self {
+        KLT {
+            window_size: 21,
+            levels: 3,
+            max_iterations: 30,
+            epsilon: 0.01,
+            min_eigen_threshold: 1e-4,
+            max_forward_backward_error: 1.0,
+        }
+    }
+}
+
+/// 单个特征点的跟踪结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackedPoint {
+    pub location: Vector2<f64>, // 在下一帧中的位置
+    pub status: bool,           // 是否跟踪成功
+    pub error: f64,             // 窗口内的平均灰度差，[0, 255]
+}
+
+impl KLT {
+    /// 把previous中的特征点跟踪到next中，结果与keypoints一一对应
+    /// 任一影像为空时全部跟踪失败
+    pub fn track(&self, previous: &GrayImage, next: &GrayImage, keypoints: &[KeyPoint]) -> Vec<TrackedPoint> {
+        if previous.width() == 0 || previous.height() == 0 || next.width() == 0 || next.height() == 0 {
+            return keypoints
+                .iter()
+                .map(|keypoint| TrackedPoint { location: keypoint.location, status: false, error: f64::INFINITY })
+                .collect();
+        }
+        let previous_pyramid = self.build_pyramid(previous);
+        let next_pyramid = self.build_pyramid(next);
+        let points: Vec<Vector2<f64>> = keypoints.iter().map(|keypoint| keypoint.location).collect();
+
+        let mut tracked = self.track_points(&previous_pyramid, &next_pyramid, &points);
+        if self.max_forward_backward_error > 0.0 {
+            let forward: Vec<Vector2<f64>> = tracked.iter().map(|point| point.location).collect();
+            let backward = self.track_points(&next_pyramid, &previous_pyramid, &forward);
+            for ((point, back), original) in tracked.iter_mut().zip(backward.iter()).zip(points.iter()) {
+                if !back.status || (back.location - original).norm() > self.max_forward_backward_error {
+                    point.status = false;
+                }
+            }
+        }
+        tracked
+    }
+
+    /// 构建浮点影像金字塔，每层先[1, 2, 1]平滑再降采样，影像过小时提前结束
+    fn build_pyramid(&self, image: &GrayImage) -> Vec<FloatImage> {
+        let base = FloatImage::from_fn(image.width(), image.height(), |x, y| Luma([image.get_pixel(x, y).0[0] as f32]));
+        let mut pyramid = vec![base];
+        for _ in 1..self.levels.max(1) {
+            let previous = pyramid.last().unwrap();
+            let (width, height) = (previous.width() / 2, previous.height() / 2);
+            if width < MIN_LEVEL_SIZE || height < MIN_LEVEL_SIZE {
+                break;
+            }
+            let pixel = |x: i32, y: i32| {
+                let x = x.clamp(0, previous.width() as i32 - 1) as u32;
+                let y = y.clamp(0, previous.height() as i32 - 1) as u32;
+                previous.get_pixel(x, y).0[0]
+            };
+            let level = FloatImage::from_fn(width, height, |x, y| {
+                let (x, y) = (2 * x as i32, 2 * y as i32);
+                let mut sum = 0.0;
+                for (dy, wy) in [(-1, 1.0), (0, 2.0), (1, 1.0)] {
+                    for (dx, wx) in [(-1, 1.0), (0, 2.0), (1, 1.0)] {
+                        sum += wx * wy * pixel(x + dx, y + dy);
+                    }
+                }
+                Luma([sum / 16.0])
+            });
+            pyramid.push(level);
+        }
+        pyramid
+    }
+
+    /// 由粗到精逐层跟踪，上一层的光流放大两倍作为下一层的初值
+    fn track_points(&self, previous: &[FloatImage], next: &[FloatImage], points: &[Vector2<f64>]) -> Vec<TrackedPoint> {
+        let levels = previous.len().min(next.len());
+        points
+            .iter()
+            .map(|point| {
+                let mut flow = Vector2::zeros();
+                for level in (0..levels).rev() {
+                    let scale = 2f64.powi(level as i32);
+                    match self.track_level(&previous[level], &next[level], point / scale, flow) {
+                        Some(level_flow) => flow = if level > 0 { level_flow * 2.0 } else { level_flow },
+                        None => return TrackedPoint { location: point + flow * scale, status: false, error: f64::INFINITY },
+                    }
+                }
+                let location = point + flow;
+                let status = is_inside(&next[0], &location);
+                let error = if status { self.window_error(&previous[0], &next[0], point, &location) } else { f64::INFINITY };
+                TrackedPoint { location, status, error }
+            })
+            .collect()
+    }
+
+    /// 在一层上迭代求解光流，flow为初值
+    /// 窗口梯度矩阵奇异或跟踪到影像外时返回None
+    fn track_level(&self, previous: &FloatImage, next: &FloatImage, point: Vector2<f64>, flow: Vector2<f64>) -> Option<Vector2<f64>> {
+        if !is_inside(previous, &point) {
+            return None;
+        }
+        let radius = (self.window_size / 2) as i32;
+        let offsets: Vec<Vector2<f64>> = (-radius..=radius)
+            .flat_map(|v| (-radius..=radius).map(move |u| Vector2::new(u as f64, v as f64)))
+            .collect();
+
+        // 模板和梯度只与上一帧有关
+        let template: Vec<f64> = offsets.iter().map(|offset| sample(previous, &(point + offset))).collect();
+        let gradients: Vec<Vector2<f64>> = offsets
+            .iter()
+            .map(|offset| {
+                let p = point + offset;
+                Vector2::new(
+                    (sample(previous, &(p + Vector2::x())) - sample(previous, &(p - Vector2::x()))) / 2.0,
+                    (sample(previous, &(p + Vector2::y())) - sample(previous, &(p - Vector2::y()))) / 2.0,
+                )
+            })
+            .collect();
+        let hessian: Matrix2<f64> = gradients.iter().map(|g| g * g.transpose()).sum();
+
+        // 最小特征值按窗口面积和灰度范围归一化
+        let normalized = hessian / (offsets.len() as f64 * 255.0 * 255.0);
+        let half_trace = normalized.trace() / 2.0;
+        let min_eigen = half_trace - (half_trace * half_trace - normalized.determinant()).max(0.0).sqrt();
+        if min_eigen < self.min_eigen_threshold {
+            return None;
+        }
+        let inverse = hessian.try_inverse()?;
+
+        let mut flow = flow;
+        for _ in 0..self.max_iterations {
+            let location = point + flow;
+            if !is_inside(next, &location) {
+                return None;
+            }
+            let b: Vector2<f64> = offsets
+                .iter()
+                .zip(template.iter().zip(gradients.iter()))
+                .map(|(offset, (value, gradient))| gradient * (value - sample(next, &(location + offset))))
+                .sum();
+            let delta = inverse * b;
+            flow += delta;
+            if delta.norm() < self.epsilon {
+                break;
+            }
+        }
+
+        Some(flow)
+    }
+
+    /// 窗口内的平均灰度差
+    fn window_error(&self, previous: &FloatImage, next: &FloatImage, point: &Vector2<f64>, location: &Vector2<f64>) -> f64 {
+        let radius = (self.window_size / 2) as i32;
+        let mut sum = 0.0;
+        let mut count = 0;
+        for v in -radius..=radius {
+            for u in -radius..=radius {
+                let offset = Vector2::new(u as f64, v as f64);
+                sum += (sample(previous, &(point + offset)) - sample(next, &(location + offset))).abs();
+                count += 1;
+            }
+        }
+        sum / count as f64
+    }
+}
+
+/// 是否在影像范围内
+fn is_inside(image: &FloatImage, point: &Vector2<f64>) -> bool {
+    point.x >= 0.0 && point.y >= 0.0 && point.x <= image.width().saturating_sub(1) as f64 && point.y <= image.height().saturating_sub(1) as f64
+}
+
+/// 双线性插值，超出影像的坐标取最近的边界像素
+fn sample(image: &FloatImage, point: &Vector2<f64>) -> f64 {
+    let max_x = image.width().saturating_sub(1) as f64;
+    let max_y = image.height().saturating_sub(1) as f64;
+    let x = point.x.clamp(0.0, max_x);
+    let y = point.y.clamp(0.0, max_y);
+    let x0 = x.floor().min(max_x - 1.0).max(0.0);
+    let y0 = y.floor().min(max_y - 1.0).max(0.0);
+    let (ax, ay) = (x - x0, y - y0);
+    let pixel = |u: f64, v: f64| image.get_pixel(u as u32, v as u32).0[0] as f64;
+    let x1 = (x0 + 1.0).min(max_x);
+    let y1 = (y0 + 1.0).min(max_y);
+
+    (1.0 - ay) * ((1.0 - ax) * pixel(x0, y0) + ax * pixel(x1, y0)) + ay * ((1.0 - ax) * pixel(x0, y1) + ax * pixel(x1, y1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gftt::GFTT;
+    use vslam_core::feature::Detector;
+
+    /// 平滑纹理，整体平移(dx, dy)
+    fn texture(dx: f64, dy: f64) -> GrayImage {
+        GrayImage::from_fn(160, 120, |x, y| {
+            let (u, v) = (x as f64 - dx, y as f64 - dy);
+            let value = 128.0 + 50.0 * (u / 7.0).sin() * (v / 9.0).cos() + 40.0 * ((u + 2.0 * v) / 13.0).sin();
+            Luma([value.round().clamp(0.0, 255.0) as u8])
+        })
+    }
+
+    #[test]
+    fn tracks_translation() {
+        let shift = Vector2::new(5.3, -3.6);
+        let previous = texture(0.0, 0.0);
+        let next = texture(shift.x, shift.y);
+        let keypoints: Vec<KeyPoint> = GFTT { max_corners: 50, ..GFTT::default() }
+            .detect(&previous)
+            .into_iter()
+            .filter(|keypoint| {
+                let location = keypoint.location;
+                location.x > 20.0 && location.y > 20.0 && location.x < 140.0 && location.y < 100.0
+            })
+            .collect();
+        assert!(keypoints.len() >= 10);
+
+        let tracked = KLT::default().track(&previous, &next, &keypoints);
+        assert_eq!(tracked.len(), keypoints.len());
+        for (keypoint, point) in keypoints.iter().zip(tracked.iter()) {
+            assert!(point.status);
+            assert!((point.location - keypoint.location - shift).norm() < 0.2, "{:?}", point);
+            assert!(point.error < 5.0);
+        }
+    }
+
+    #[test]
+    fn rejects_untrackable_points() {
+        let previous = GrayImage::from_pixel(80, 80, Luma([100]));
+        let tracked = KLT::default().track(&previous, &previous, &[KeyPoint::new(Vector2::new(40.0, 40.0))]);
+        assert!(!tracked[0].status);
+
+        let empty = GrayImage::new(0, 0);
+        for (previous, next) in [(&empty, &previous), (&previous, &empty)] {
+            let tracked = KLT::default().track(previous, next, &[KeyPoint::new(Vector2::new(0.0, 0.0))]);
+            assert!(!tracked[0].status);
+        }
+
+        // 下一帧内容完全不同，正反向跟踪不一致
+        let previous = texture(0.0, 0.0);
+        let next = GrayImage::from_fn(160, 120, |x, y| Luma([((x * 37 + y * 91) % 256) as u8]));
+        let keypoints = GFTT { max_corners: 20, ..GFTT::default() }.detect(&previous);
+        let tracked = KLT::default().track(&previous, &next, &keypoints);
+        let num_tracked = tracked.iter().filter(|point| point.status).count();
+        assert!(num_tracked * 2 < keypoints.len(), "{} / {}", num_tracked, keypoints.len());
+    }
+}
@@ -1 +1,15 @@
+pub mod ann;
+pub mod essential;
+pub mod fast;
+pub mod gftt;
+pub mod hamming;
+pub mod klt;
+pub mod matcher;
+pub mod orb;
+pub mod pnp;
+pub mod projection;
+pub mod ransac;
+pub mod rng;
 pub mod sift;
+pub mod surf;
+pub mod two_view;
\ No newline at end of file
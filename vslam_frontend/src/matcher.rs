@@ -0,0 +1,235 @@
+use crate::hamming::HammingKernel;
+use vslam_core::feature::{Descriptor, KeyPoint, Matcher};
+use std::f64::consts::PI;
+
+/// 旋转一致性直方图的bin数
+const ROTATION_HISTOGRAM_BINS: usize = 30;
+
+/// 一对匹配
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DMatch {
+    pub query: usize,  // query描述子索引
+    pub train: usize,  // train描述子索引
+    pub distance: f64, // 描述子距离
+}
+
+/// 暴力匹配器，二进制描述子时距离为汉明距离
+#[derive(Clone, Copy, Debug)]
+pub struct BFMatcher {
+    pub max_distance: Option<f64>,    // 距离上限，超过的匹配被舍弃
+    pub ratio_threshold: Option<f64>, // 比值测试阈值，None时不做比值测试
+    pub cross_check: bool,            // 是否要求互为最近邻
+}
+
+impl Default for BFMatcher {
+    fn default() -> Self {
+        BFMatcher { max_distance: None, ratio_threshold: Some(0.8), cross_check: false }
+    }
+}
+
+impl BFMatcher {
+    /// 每个query描述子的k个最近邻，按距离从小到大排列
+    /// 只受max_distance约束，不做比值测试和互相检查
+    pub fn knn_match<D: Descriptor>(&self, query: &[D], train: &[D], k: usize) -> Vec<Vec<DMatch>> {
+        query
+            .iter()
+            .enumerate()
+            .map(|(i, query_descriptor)| {
+                let mut neighbors: Vec<DMatch> = Vec::with_capacity(k + 1);
+                for (j, train_descriptor) in train.iter().enumerate() {
+                    let distance = query_descriptor.distance(train_descriptor);
+                    if self.max_distance.is_some_and(|max_distance| distance > max_distance) {
+                        continue;
+                    }
+                    if neighbors.len() == k && neighbors.last().is_none_or(|last| distance >= last.distance) {
+                        continue;
+                    }
+                    let position = neighbors.partition_point(|neighbor| neighbor.distance <= distance);
+                    neighbors.insert(position, DMatch { query: i, train: j, distance });
+                    neighbors.truncate(k);
+                }
+                neighbors
+            })
+            .collect()
+    }
+
+    /// 最近邻匹配，依次做距离上限、比值测试和互相检查
+    pub fn match_all<D: Descriptor>(&self, query: &[D], train: &[D]) -> Vec<DMatch> {
+        // 比值测试需要次近邻，但次近邻不受距离上限约束
+        let unlimited = BFMatcher { max_distance: None, ..*self };
+        let k = if self.ratio_threshold.is_some() { 2 } else { 1 };
+        let reverse = if self.cross_check { unlimited.knn_match(train, query, 1) } else { Vec::new() };
+
+        unlimited
+            .knn_match(query, train, k)
+            .into_iter()
+            .filter_map(|neighbors| {
+                let best = *neighbors.first()?;
+                if self.max_distance.is_some_and(|max_distance| best.distance > max_distance) {
+                    return None;
+                }
+                if let (Some(ratio), Some(second)) = (self.ratio_threshold, neighbors.get(1)) {
+                    if best.distance >= ratio * second.distance {
+                        return None;
+                    }
+                }
+                if self.cross_check && reverse[best.train].first().map(|back| back.train) != Some(best.query) {
+                    return None;
+                }
+                Some(best)
+            })
+            .collect()
+    }
+}
+
+impl BFMatcher {
+    /// 二进制描述子的快速匹配，结果与match_all相同
+    /// 使用当前CPU支持的最快汉明距离实现
+    pub fn match_binary<const N: usize>(&self, query: &[[u64; N]], train: &[[u64; N]]) -> Vec<DMatch> {
+        self.match_binary_with(HammingKernel::detect(), query, train)
+    }
+
+    /// 使用指定的汉明距离实现匹配
+    pub fn match_binary_with<const N: usize>(&self, kernel: HammingKernel, query: &[[u64; N]], train: &[[u64; N]]) -> Vec<DMatch> {
+        let reverse: Vec<Option<usize>> = if self.cross_check {
+            train.iter().map(|descriptor| kernel.nearest_two(descriptor, query).index).collect()
+        } else {
+            Vec::new()
+        };
+
+        query
+            .iter()
+            .enumerate()
+            .filter_map(|(i, descriptor)| {
+                let nearest = kernel.nearest_two(descriptor, train);
+                let j = nearest.index?;
+                let best = nearest.best as f64;
+                if self.max_distance.is_some_and(|max_distance| best > max_distance) {
+                    return None;
+                }
+                if let Some(ratio) = self.ratio_threshold {
+                    if nearest.second != u32::MAX && best >= ratio * nearest.second as f64 {
+                        return None;
+                    }
+                }
+                if self.cross_check && reverse[j] != Some(i) {
+                    return None;
+                }
+                Some(DMatch { query: i, train: j, distance: best })
+            })
+            .collect()
+    }
+}
+
+impl<D: Descriptor> Matcher<D> for BFMatcher {
+    fn match_descriptors(&self, query: &[D], train: &[D]) -> Vec<(usize, usize)> {
+        self.match_all(query, train)
+            .into_iter()
+            .map(|m| (m.query, m.train))
+            .collect()
+    }
+}
+
+/// 旋转一致性检查，与ORB-SLAM相同
+/// 统计匹配特征点主方向之差的直方图，只保留落在数量最多的三个bin中的匹配，
+/// 数量不到最多者10%的bin也被舍弃
+pub fn filter_by_rotation(matches: &[DMatch], query_keypoints: &[KeyPoint], train_keypoints: &[KeyPoint]) -> Vec<DMatch> {
+    let bin_of = |m: &DMatch| {
+        let rotation = (query_keypoints[m.query].angle - train_keypoints[m.train].angle).rem_euclid(2.0 * PI);
+        (rotation / (2.0 * PI) * ROTATION_HISTOGRAM_BINS as f64).round() as usize % ROTATION_HISTOGRAM_BINS
+    };
+
+    let mut histogram = [0usize; ROTATION_HISTOGRAM_BINS];
+    for m in matches {
+        histogram[bin_of(m)] += 1;
+    }
+
+    let mut bins: Vec<usize> = (0..ROTATION_HISTOGRAM_BINS).collect();
+    bins.sort_by_key(|&bin| std::cmp::Reverse(histogram[bin]));
+    let max_count = histogram[bins[0]];
+    let kept: Vec<usize> = bins
+        .into_iter()
+        .take(3)
+        .filter(|&bin| histogram[bin] > 0 && histogram[bin] as f64 >= 0.1 * max_count as f64)
+        .collect();
+
+    matches
+        .iter()
+        .filter(|m| kept.contains(&bin_of(m)))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn knn_is_sorted() {
+        let query = [[0b0000u64]];
+        let train = [[0b0111u64], [0b0001], [0b1111], [0b0011]];
+        let neighbors = BFMatcher::default().knn_match(&query, &train, 3);
+        let trains: Vec<usize> = neighbors[0].iter().map(|m| m.train).collect();
+        assert_eq!(trains, vec![1, 3, 0]);
+        assert_eq!(neighbors[0][0].distance, 1.0);
+    }
+
+    #[test]
+    fn filters_by_distance_ratio_and_cross_check() {
+        let query = [[0x00u64], [0xff], [0xf0f0]];
+        let train = [[0x01u64], [0xfe], [0xfc]];
+
+        let plain = BFMatcher { ratio_threshold: None, ..BFMatcher::default() };
+        assert_eq!(plain.match_all(&query, &train).len(), 3);
+
+        // 0xff到0xfe和0xfc的距离为1和2，比值0.5
+        let ratio = BFMatcher { ratio_threshold: Some(0.4), ..BFMatcher::default() };
+        assert!(ratio.match_all(&query, &train).iter().all(|m| m.query != 1));
+
+        let capped = BFMatcher { max_distance: Some(2.0), ratio_threshold: None, cross_check: false };
+        assert!(capped.match_all(&query, &train).iter().all(|m| m.query != 2));
+
+        // 0xf0f0的最近邻0xfc反过来以0xff为最近邻
+        let cross = BFMatcher { ratio_threshold: None, cross_check: true, ..BFMatcher::default() };
+        let matches = cross.match_descriptors(&query, &train);
+        assert_eq!(matches, vec![(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn binary_matching_agrees_with_generic() {
+        let mut state = 7u64;
+        let mut descriptors = |count: usize| -> Vec<[u64; 4]> { (0..count).map(|_| [0; 4].map(|_: u64| split_mix64(&mut state))).collect() };
+        let train = descriptors(60);
+        // 一半query由train翻转少量位得到，保证有可通过比值测试的匹配
+        let mut query = descriptors(30);
+        for (i, descriptor) in train.iter().take(30).enumerate().filter(|(i, _)| i % 2 == 0) {
+            query[i] = [descriptor[0] ^ 0b1011, descriptor[1], descriptor[2] ^ 1, descriptor[3]];
+        }
+
+        for cross_check in [false, true] {
+            for ratio_threshold in [None, Some(0.8)] {
+                let matcher = BFMatcher { max_distance: Some(100.0), ratio_threshold, cross_check };
+                let expected = matcher.match_all(&query, &train);
+                for kernel in [HammingKernel::Scalar, HammingKernel::Popcnt, HammingKernel::Avx2] {
+                    assert_eq!(matcher.match_binary_with(kernel, &query, &train), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_filter_removes_inconsistent_matches() {
+        let keypoint = |angle: f64| KeyPoint { angle, ..KeyPoint::new(Vector2::zeros()) };
+        let query: Vec<KeyPoint> = (0..17).map(|i| keypoint(0.1 * i as f64)).collect();
+        // 前15个整体旋转0.5弧度，后2个方向各不相同
+        let train: Vec<KeyPoint> = (0..17)
+            .map(|i| if i < 15 { keypoint(0.1 * i as f64 - 0.5) } else { keypoint(0.1 * i as f64 + 2.0 * (i - 14) as f64) })
+            .collect();
+        let matches: Vec<DMatch> = (0..17).map(|i| DMatch { query: i, train: i, distance: 0.0 }).collect();
+        let filtered = filter_by_rotation(&matches, &query, &train);
+        assert_eq!(filtered.len(), 15);
+        assert!(filtered.iter().all(|m| m.query < 15));
+    }
+}
@@ -1,170 +1,600 @@
-use image::{DynamicImage, GrayImage};
+use image::imageops::{resize, FilterType};
+use image::GrayImage;
+use imageproc::filter::gaussian_blur_f32;
 use nalgebra::Vector2;
-use rand::Rng;
-use vslam_core::keyframe::Feature;
+use std::f64::consts::PI;
+use vslam_core::feature::{DescriptorExtractor, Detector, KeyPoint, Matcher};
+use crate::fast::{distribute_quadtree, FAST};
+use crate::gftt::{harris_response, structure_tensor};
+use crate::matcher::BFMatcher;
+use crate::rng::split_mix64;
 
-#[derive(Clone, Copy)]
+/// BRIEF采样区域边长
+const PATCH_SIZE: i32 = 31;
+/// 灰度质心的统计半径
+const HALF_PATCH_SIZE: i32 = 15;
+/// 各层影像上检测特征点时忽略的边界宽度，保证旋转后的采样点仍在影像内
+const EDGE_THRESHOLD: i32 = 19;
+/// Harris响应的统计窗口边长
+const HARRIS_BLOCK_SIZE: u32 = 7;
+/// Harris响应的经验系数
+const HARRIS_K: f64 = 0.04;
+/// 计算描述子前的高斯平滑σ
+const BRIEF_SIGMA: f32 = 2.0;
+/// 采样点坐标范围，旋转后到中心的距离不超过PATTERN_RADIUS·√2 ≈ 18.4，小于EDGE_THRESHOLD
+const PATTERN_RADIUS: i8 = 13;
+
+/// ORB特征：多尺度FAST角点 + 灰度质心方向 + rBRIEF描述子，汉明距离匹配
+#[derive(Clone, Copy, Debug)]
 pub struct ORB{
-    pub location:Vector2<f64>,
-    pub descriptor:[u64;4],
+    pub n_features:usize,// 各层特征点总数上限
+    pub scale_factor:f64,// 金字塔相邻层的缩放比例
+    pub n_levels:usize,// 金字塔层数
+    pub fast_threshold:u8,// FAST阈值
+    pub ratio_threshold:f64,// 匹配时的比值测试阈值
+    pub pattern:BriefPattern,// BRIEF采样点对，描述子只有在相同pattern下才可比较
 }
 
-impl Feature for ORB {
-    fn extract_features(image:&DynamicImage)->Vec<Self> where Self:Sized {
-        let gray_image=image.to_luma8();
-        let key_points=fast(&gray_image);
-        let descriptors=brief(&gray_image,&key_points);
-        key_points.into_iter().zip(descriptors).map(|(location, descriptor)|{ORB { location, descriptor}})
-        .collect()
-    }
-
-    fn match_features(feature1:&Vec<Self>,feature2:&Vec<Self>)->Vec<(usize,usize)> where Self:Sized {
-        let ratio_threshold = 0.8;
-        let mut matches = Vec::new();
-
-        for (i, feature1_descriptor) in feature1.iter().enumerate() {
-            let mut best_distance = std::u32::MAX;
-            let mut second_best_distance = std::u32::MAX;
-            let mut best_index = None;
-
-            // 保留最小的两个
-            for (j, feature2_descriptor) in feature2.iter().enumerate() {
-                let distance = hamming_distance(&feature1_descriptor.descriptor, &feature2_descriptor.descriptor);
-                if distance < best_distance {
-                    second_best_distance = best_distance;
-                    best_distance = distance;
-                    best_index = Some(j);
-                } else if distance < second_best_distance {
-                    second_best_distance = distance;
-                }
-            }
-            
-            // 比值测试
-            if best_distance < (ratio_threshold * second_best_distance as f64) as u32 {
-                if let Some(best_j) = best_index {
-                    matches.push((i, best_j));
-                }
+impl Default for ORB {
+    fn default() -> Self {
+        ORB { n_features: 500, scale_factor: 1.2, n_levels: 8, fast_threshold: 20, ratio_threshold: 0.8, pattern: BriefPattern::Learned }
+    }
+}
+
+/// BRIEF采样点对的来源
+/// 与提取器一起保存即可保证不同帧、不同进程和保存的地图中的描述子可比较
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BriefPattern {
+    /// ORB论文学习得到的采样点对，与OpenCV一致
+    #[default]
+    Learned,
+    /// 由种子生成的均匀分布采样点对，只使用整数运算，与平台无关
+    Seeded(u64),
+}
+
+impl BriefPattern {
+    /// 256个采样点对(x1, y1, x2, y2)，坐标在[-13, 13]内
+    pub fn pairs(&self) -> Vec<[i8; 4]> {
+        match *self {
+            BriefPattern::Learned => BIT_PATTERN_31.to_vec(),
+            BriefPattern::Seeded(seed) => {
+                let mut state = seed;
+                let range = 2 * PATTERN_RADIUS as u64 + 1;
+                (0..BIT_PATTERN_31.len())
+                    .map(|_| [0; 4].map(|_: i8| (split_mix64(&mut state) % range) as i8 - PATTERN_RADIUS))
+                    .collect()
             }
         }
+    }
+}
 
-        matches
+impl ORB {
+    /// 提取特征点和描述子，FAST检测与rBRIEF描述子在同一影像金字塔的对应层上进行
+    pub fn extract(&self, image:&GrayImage)->(Vec<KeyPoint>, Vec<[u64;4]>) {
+        let pyramid = self.build_pyramid(image);
+        let mut keypoints = self.detect_in_pyramid(&pyramid);
+        let descriptors = self.compute_in_pyramid(&pyramid, &mut keypoints);
+        (keypoints, descriptors)
     }
 
-}
+    /// 第level层相对原图的缩放倍数
+    pub fn level_scale(&self, level:usize)->f64 {
+        self.scale_factor.powi(level as i32)
+    }
 
-/// fast角点检测
-fn fast(image: &GrayImage) -> Vec<Vector2<f64>> {
-    let threshold:u8=20;
-    let border=3;
-    let width = image.width();
-    let height=image.height();
-    let mut keypoints:Vec<Vector2<f64>>=Vec::new();
-
-    // 通过is_corner_fast检测的，加入keypoints中
-    for y in border..(height-border){
-        for x in border..(width-border){
-            let pixel_value=image.get_pixel(x,y).0[0];
-            if is_corner_fast(image, x, y, pixel_value, threshold) {
-                keypoints.push(Vector2::new(x as f64, y as f64));
+    /// 构建影像金字塔，影像过小时提前结束
+    fn build_pyramid(&self, image:&GrayImage)->Vec<GrayImage> {
+        let min_size = (2 * EDGE_THRESHOLD + 1) as u32;
+        let mut pyramid = vec![image.clone()];
+        for level in 1..self.n_levels {
+            let scale = self.level_scale(level);
+            let width = (image.width() as f64 / scale).round() as u32;
+            let height = (image.height() as f64 / scale).round() as u32;
+            if width < min_size || height < min_size {
+                break;
             }
+            pyramid.push(resize(image, width, height, FilterType::Triangle));
         }
+        pyramid
     }
 
-    keypoints
-}
-
-/// 判断输入是否是fast角点
-fn is_corner_fast(image: &GrayImage, x: u32, y: u32, pixel_value: u8, threshold: u8) -> bool {
-    let offsets: [(i32, i32); 16] = [(0, 3),(1, 3),        (2, 2),        (3, 1),        (3, 0),        (3, -1),        (2, -2),        (1, -3),        (0, -3),        (-1, -3),        (-2, -2),        (-3, -1),        (-3, 0),        (-3, 1),        (-2, 2),        (-1, 3),    ];
-
-    let darker = |value| (value < pixel_value) && ( pixel_value -value > threshold);
-    let brighter = |value| (value > pixel_value) && (value - pixel_value > threshold);
-
-    let darker_brighter:Vec<(bool,bool)>= offsets
-        .iter()
-        .map(|&(dx, dy)| {
-            let pixel = image.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32).0[0];
-            (darker(pixel), brighter(pixel))
-        })
-        .collect::<Vec<_>>();
-
-    let consecutive_darker_or_brighter = |count: usize| -> bool {
-        let mut consecutive_darker = 0;
-        let mut consecutive_brighter = 0;
-        for &(darker, brighter) in darker_brighter.iter().cycle().take(darker_brighter.len() * 2) {
-            if darker {
-                consecutive_darker += 1;
-                consecutive_brighter = 0;
-            } else if brighter {
-                consecutive_brighter += 1;
-                consecutive_darker = 0;
+    /// 各层分配的特征点数，与该层面积成比例，按等比数列递减
+    fn features_per_level(&self, n_levels:usize)->Vec<usize> {
+        let factor = 1.0 / self.scale_factor;
+        let first = self.n_features as f64 * (1.0 - factor) / (1.0 - factor.powi(n_levels as i32));
+        let mut features = Vec::with_capacity(n_levels);
+        let mut total = 0;
+        for level in 0..n_levels {
+            let n = if level + 1 == n_levels {
+                self.n_features.saturating_sub(total)
             } else {
-                consecutive_darker = 0;
-                consecutive_brighter = 0;
+                (first * factor.powi(level as i32)).round() as usize
+            };
+            total += n;
+            features.push(n);
+        }
+        features
+    }
+
+    /// 在各层检测FAST角点，用四叉树均匀选出Harris响应最好的若干个，并计算主方向
+    /// 返回的特征点坐标已换算到原图
+    fn detect_in_pyramid(&self, pyramid:&[GrayImage])->Vec<KeyPoint> {
+        let detector = FAST { threshold: self.fast_threshold, ..FAST::default() };
+        let features_per_level = self.features_per_level(pyramid.len());
+        let mut keypoints = Vec::new();
+
+        for (level, image) in pyramid.iter().enumerate() {
+            let width = image.width() as i32;
+            let height = image.height() as i32;
+            let mut level_keypoints = detector.detect(image);
+            level_keypoints.retain(|keypoint| {
+                let x = keypoint.location.x as i32;
+                let y = keypoint.location.y as i32;
+                x >= EDGE_THRESHOLD && x < width - EDGE_THRESHOLD && y >= EDGE_THRESHOLD && y < height - EDGE_THRESHOLD
+            });
+            for keypoint in level_keypoints.iter_mut() {
+                let tensor = structure_tensor(image, keypoint.location.x as i32, keypoint.location.y as i32, HARRIS_BLOCK_SIZE);
+                keypoint.response = harris_response(tensor, HARRIS_K);
             }
-            if consecutive_darker >= count || consecutive_brighter >= count {
-                return true;
+            let min = Vector2::new(EDGE_THRESHOLD as f64, EDGE_THRESHOLD as f64);
+            let max = Vector2::new((width - EDGE_THRESHOLD) as f64, (height - EDGE_THRESHOLD) as f64);
+            let level_keypoints = distribute_quadtree(level_keypoints, min, max, features_per_level[level]);
+
+            let scale = self.level_scale(level);
+            for keypoint in level_keypoints {
+                let x = keypoint.location.x as i32;
+                let y = keypoint.location.y as i32;
+                keypoints.push(KeyPoint {
+                    location: keypoint.location * scale,
+                    size: PATCH_SIZE as f64 * scale,
+                    angle: intensity_centroid_angle(image, x, y),
+                    response: keypoint.response,
+                    octave: level,
+                });
             }
         }
-        false
-    };
 
+        keypoints
+    }
 
-    consecutive_darker_or_brighter(9) 
-}
+    /// 在特征点所在层的平滑影像上计算rBRIEF描述子
+    /// 靠近边界无法计算描述子的特征点会被移除
+    fn compute_in_pyramid(&self, pyramid:&[GrayImage], keypoints:&mut Vec<KeyPoint>)->Vec<[u64;4]> {
+        let smoothed: Vec<GrayImage> = pyramid.iter().map(|image| gaussian_blur_f32(image, BRIEF_SIGMA)).collect();
+        let pairs = self.pattern.pairs();
+        let mut kept = Vec::with_capacity(keypoints.len());
+        let mut descriptors = Vec::with_capacity(keypoints.len());
 
-/// brief描述子
-fn brief(image: &GrayImage, keypoints: &[Vector2<f64>]) -> Vec<[u64; 4]> {
-    let patch_size = 31;
-    let border = patch_size / 2;
-    let width = image.width();
-    let height = image.height();
-    let mut rng = rand::thread_rng();
-    let random_points = (0..256)
-        .map(|_| {
-            (
-                rng.gen_range(-border..=border),
-                rng.gen_range(-border..=border),
-            )
-        })
-        .collect::<Vec<_>>();
-
-    keypoints
-        .iter()
-        .map(|&keypoint| {
-            let x = keypoint[0] as i32;
-            let y = keypoint[1] as i32;
-
-            if x - border < 0
-                || x + border >= width as i32
-                || y - border < 0
-                || y + border >= height as i32
+        for keypoint in keypoints.iter() {
+            let level = keypoint.octave.min(smoothed.len() - 1);
+            let image = &smoothed[level];
+            let location: Vector2<f64> = keypoint.location / self.level_scale(level);
+            let x = location.x.round() as i32;
+            let y = location.y.round() as i32;
+            if x < EDGE_THRESHOLD
+                || y < EDGE_THRESHOLD
+                || x >= image.width() as i32 - EDGE_THRESHOLD
+                || y >= image.height() as i32 - EDGE_THRESHOLD
             {
-                return [0; 4];
+                continue;
             }
+            kept.push(*keypoint);
+            descriptors.push(steered_brief(image, x, y, keypoint.angle, &pairs));
+        }
+
+        *keypoints = kept;
+        descriptors
+    }
+}
+
+impl Detector for ORB {
+    /// 检测到的特征点已分配主方向，octave为所在金字塔层
+    fn detect(&self, image:&GrayImage)->Vec<KeyPoint> {
+        let pyramid = self.build_pyramid(image);
+        self.detect_in_pyramid(&pyramid)
+    }
+}
+
+impl DescriptorExtractor for ORB {
+    type Descriptor = [u64; 4];
 
-            let mut descriptor = [0u64; 4];
-            for (i, &(dx, dy)) in random_points.iter().enumerate() {
-                let pos1 = (x + dx, y + dy);
-                let pos2 = (x - dx, y - dy);
-                let intensity1 = image.get_pixel(pos1.0 as u32, pos1.1 as u32).0[0];
-                let intensity2 = image.get_pixel(pos2.0 as u32, pos2.1 as u32).0[0];
-                if intensity1 < intensity2 {
-                    let idx = i / 64;
-                    let bit = i % 64;
-                    descriptor[idx] |= 1u64 << bit;
-                }
+    /// 使用特征点已有的octave和angle
+    fn compute(&self, image:&GrayImage, keypoints:&mut Vec<KeyPoint>)->Vec<[u64;4]> {
+        let pyramid = self.build_pyramid(image);
+        self.compute_in_pyramid(&pyramid, keypoints)
+    }
+}
+
+impl Matcher<[u64;4]> for ORB {
+    /// 汉明距离最近邻匹配，只做比值测试
+    /// 需要距离上限、互相检查或kNN时使用BFMatcher
+    fn match_descriptors(&self, query:&[[u64;4]], train:&[[u64;4]])->Vec<(usize,usize)> {
+        BFMatcher { ratio_threshold: Some(self.ratio_threshold), ..BFMatcher::default() }
+            .match_binary(query, train)
+            .into_iter()
+            .map(|m| (m.query, m.train))
+            .collect()
+    }
+}
+
+/// 灰度质心法计算主方向
+/// 半径HALF_PATCH_SIZE的圆形区域内，中心指向灰度质心的方向
+fn intensity_centroid_angle(image: &GrayImage, x: i32, y: i32) -> f64 {
+    let mut m01 = 0.0;
+    let mut m10 = 0.0;
+    for v in -HALF_PATCH_SIZE..=HALF_PATCH_SIZE {
+        for u in -HALF_PATCH_SIZE..=HALF_PATCH_SIZE {
+            if u * u + v * v > HALF_PATCH_SIZE * HALF_PATCH_SIZE {
+                continue;
             }
-            descriptor
-        })
-        .collect()
+            let intensity = image.get_pixel((x + u) as u32, (y + v) as u32).0[0] as f64;
+            m10 += u as f64 * intensity;
+            m01 += v as f64 * intensity;
+        }
+    }
+    m01.atan2(m10).rem_euclid(2.0 * PI)
 }
 
-/// 计算汉明距离
-fn hamming_distance(a: &[u64; 4], b: &[u64; 4]) -> u32 {
-    let mut distance = 0;
-    for i in 0..4 {
-        distance += (a[i] ^ b[i]).count_ones();
+/// rBRIEF描述子
+/// 采样点对按主方向旋转后比较平滑影像的灰度，第i位为1表示第一个点比第二个点暗
+fn steered_brief(image: &GrayImage, x: i32, y: i32, angle: f64, pairs: &[[i8; 4]]) -> [u64; 4] {
+    let (sin_angle, cos_angle) = angle.sin_cos();
+    let sample = |u: i8, v: i8| {
+        let (u, v) = (u as f64, v as f64);
+        let dx = (u * cos_angle - v * sin_angle).round() as i32;
+        let dy = (u * sin_angle + v * cos_angle).round() as i32;
+        image.get_pixel((x + dx) as u32, (y + dy) as u32).0[0]
+    };
+
+    let mut descriptor = [0u64; 4];
+    for (i, &[x1, y1, x2, y2]) in pairs.iter().enumerate() {
+        if sample(x1, y1) < sample(x2, y2) {
+            descriptor[i / 64] |= 1u64 << (i % 64);
+        }
     }
-    distance
-}
\ No newline at end of file
+    descriptor
+}
+
+/// ORB论文中在31x31邻域上学习得到的rBRIEF采样点对(x1, y1, x2, y2)
+/// 按方差大、相关性小的顺序排列，与OpenCV的bit_pattern_31一致
+#[rustfmt::skip]
+const BIT_PATTERN_31: [[i8; 4]; 256] = [
+    [8, -3, 9, 5],
+    [4, 2, 7, -12],
+    [-11, 9, -8, 2],
+    [7, -12, 12, -13],
+    [2, -13, 2, 12],
+    [1, -7, 1, 6],
+    [-2, -10, -2, -4],
+    [-13, -13, -11, -8],
+    [-13, -3, -12, -9],
+    [10, 4, 11, 9],
+    [-13, -8, -8, -9],
+    [-11, 7, -9, 12],
+    [7, 7, 12, 6],
+    [-4, -5, -3, 0],
+    [-13, 2, -12, -3],
+    [-9, 0, -7, 5],
+    [12, -6, 12, -1],
+    [-3, 6, -2, 12],
+    [-6, -13, -4, -8],
+    [11, -13, 12, -8],
+    [4, 7, 5, 1],
+    [5, -3, 10, -3],
+    [3, -7, 6, 12],
+    [-8, -7, -6, -2],
+    [-2, 11, -1, -10],
+    [-13, 12, -8, 10],
+    [-7, 3, -5, -3],
+    [-4, 2, -3, 7],
+    [-10, -12, -6, 11],
+    [5, -12, 6, -7],
+    [5, -6, 7, -1],
+    [1, 0, 4, -5],
+    [9, 11, 11, -13],
+    [4, 7, 4, 12],
+    [2, -1, 4, 4],
+    [-4, -12, -2, 7],
+    [-8, -5, -7, -10],
+    [4, 11, 9, 12],
+    [0, -8, 1, -13],
+    [-13, -2, -8, 2],
+    [-3, -2, -2, 3],
+    [-6, 9, -4, -9],
+    [8, 12, 10, 7],
+    [0, 9, 1, 3],
+    [7, -5, 11, -10],
+    [-13, -6, -11, 0],
+    [10, 7, 12, 1],
+    [-6, -3, -6, 12],
+    [10, -9, 12, -4],
+    [-13, 8, -8, -12],
+    [-13, 0, -8, -4],
+    [3, 3, 7, 8],
+    [5, 7, 10, -7],
+    [-1, 7, 1, -12],
+    [3, -10, 5, 6],
+    [2, -4, 3, -10],
+    [-13, 0, -13, 5],
+    [-13, -7, -12, 12],
+    [-13, 3, -11, 8],
+    [-7, 12, -4, 7],
+    [6, -10, 12, 8],
+    [-9, -1, -7, -6],
+    [-2, -5, 0, 12],
+    [-12, 5, -7, 5],
+    [3, -10, 8, -13],
+    [-7, -7, -4, 5],
+    [-3, -2, -1, -7],
+    [2, 9, 5, -11],
+    [-11, -13, -5, -13],
+    [-1, 6, 0, -1],
+    [5, -3, 5, 2],
+    [-4, -13, -4, 12],
+    [-9, -6, -9, 6],
+    [-12, -10, -8, -4],
+    [10, 2, 12, -3],
+    [7, 12, 12, 12],
+    [-7, -13, -6, 5],
+    [-4, 9, -3, 4],
+    [7, -1, 12, 2],
+    [-7, 6, -5, 1],
+    [-13, 11, -12, 5],
+    [-3, 7, -2, -6],
+    [7, -8, 12, -7],
+    [-13, -7, -11, -12],
+    [1, -3, 12, 12],
+    [2, -6, 3, 0],
+    [-4, 3, -2, -13],
+    [-1, -13, 1, 9],
+    [7, 1, 8, -6],
+    [1, -1, 3, 12],
+    [9, 1, 12, 6],
+    [-1, -9, -1, 3],
+    [-13, -13, -10, 5],
+    [7, 7, 10, 12],
+    [12, -5, 12, 9],
+    [6, 3, 7, 11],
+    [5, -13, 6, 10],
+    [2, -12, 2, 3],
+    [3, 8, 4, -6],
+    [2, 6, 12, -13],
+    [9, -12, 10, 3],
+    [-8, 4, -7, 9],
+    [-11, 12, -4, -6],
+    [1, 12, 2, -8],
+    [6, -9, 7, -4],
+    [2, 3, 3, -2],
+    [6, 3, 11, 0],
+    [3, -3, 8, -8],
+    [7, 8, 9, 3],
+    [-11, -5, -6, -4],
+    [-10, 11, -5, 10],
+    [-5, -8, -3, 12],
+    [-10, 5, -9, 0],
+    [8, -1, 12, -6],
+    [4, -6, 6, -11],
+    [-10, 12, -8, 7],
+    [4, -2, 6, 7],
+    [-2, 0, -2, 12],
+    [-5, -8, -5, 2],
+    [7, -6, 10, 12],
+    [-9, -13, -8, -8],
+    [-5, -13, -5, -2],
+    [8, -8, 9, -13],
+    [-9, -11, -9, 0],
+    [1, -8, 1, -2],
+    [7, -4, 9, 1],
+    [-2, 1, -1, -4],
+    [11, -6, 12, -11],
+    [-12, -9, -6, 4],
+    [3, 7, 7, 12],
+    [5, 5, 10, 8],
+    [0, -4, 2, 8],
+    [-9, 12, -5, -13],
+    [0, 7, 2, 12],
+    [-1, 2, 1, 7],
+    [5, 11, 7, -9],
+    [3, 5, 6, -8],
+    [-13, -4, -8, 9],
+    [-5, 9, -3, -3],
+    [-4, -7, -3, -12],
+    [6, 5, 8, 0],
+    [-7, 6, -6, 12],
+    [-13, 6, -5, -2],
+    [1, -10, 3, 10],
+    [4, 1, 8, -4],
+    [-2, -2, 2, -13],
+    [2, -12, 12, 12],
+    [-2, -13, 0, -6],
+    [4, 1, 9, 3],
+    [-6, -10, -3, -5],
+    [-3, -13, -1, 1],
+    [7, 5, 12, -11],
+    [4, -2, 5, -7],
+    [-13, 9, -9, -5],
+    [7, 1, 8, 6],
+    [7, -8, 7, 6],
+    [-7, -4, -7, 1],
+    [-8, 11, -7, -8],
+    [-13, 6, -12, -8],
+    [2, 4, 3, 9],
+    [10, -5, 12, 3],
+    [-6, -5, -6, 7],
+    [8, -3, 9, -8],
+    [2, -12, 2, 8],
+    [-11, -2, -10, 3],
+    [-12, -13, -7, -9],
+    [-11, 0, -10, -5],
+    [5, -3, 11, 8],
+    [-2, -13, -1, 12],
+    [-1, -8, 0, 9],
+    [-13, -11, -12, -5],
+    [-10, -2, -10, 11],
+    [-3, 9, -2, -13],
+    [2, -3, 3, 2],
+    [-9, -13, -4, 0],
+    [-4, 6, -3, -10],
+    [-4, 12, -2, -7],
+    [-6, -11, -4, 9],
+    [6, -3, 6, 11],
+    [-13, 11, -5, 5],
+    [11, 11, 12, 6],
+    [7, -5, 12, -2],
+    [-1, 12, 0, 7],
+    [-4, -8, -3, -2],
+    [-7, 1, -6, 7],
+    [-13, -12, -8, -13],
+    [-7, -2, -6, -8],
+    [-8, 5, -6, -9],
+    [-5, -1, -4, 5],
+    [-13, 7, -8, 10],
+    [1, 5, 5, -13],
+    [1, 0, 10, -13],
+    [9, 12, 10, -1],
+    [5, -8, 10, -9],
+    [-1, 11, 1, -13],
+    [-9, -3, -6, 2],
+    [-1, -10, 1, 12],
+    [-13, 1, -8, -10],
+    [8, -11, 10, -6],
+    [2, -13, 3, -6],
+    [7, -13, 12, -9],
+    [-10, -10, -5, -7],
+    [-10, -8, -8, -13],
+    [4, -6, 8, 5],
+    [3, 12, 8, -13],
+    [-4, 2, -3, -3],
+    [5, -13, 10, -12],
+    [4, -13, 5, -1],
+    [-9, 9, -4, 3],
+    [0, 3, 3, -9],
+    [-12, 1, -6, 1],
+    [3, 2, 4, -8],
+    [-10, -10, -10, 9],
+    [8, -13, 12, 12],
+    [-8, -12, -6, -5],
+    [2, 2, 3, 7],
+    [10, 6, 11, -8],
+    [6, 8, 8, -12],
+    [-7, 10, -6, 5],
+    [-3, -9, -3, 9],
+    [-1, -13, -1, 5],
+    [-3, -7, -3, 4],
+    [-8, -2, -8, 3],
+    [4, 2, 12, 12],
+    [2, -5, 3, 11],
+    [6, -9, 11, -13],
+    [3, -1, 7, 12],
+    [11, -1, 12, 4],
+    [-3, 0, -3, 6],
+    [4, -11, 4, 12],
+    [2, -4, 2, 1],
+    [-10, -6, -8, 1],
+    [-13, 7, -11, 1],
+    [-13, 12, -11, -13],
+    [6, 0, 11, -13],
+    [0, -1, 1, 4],
+    [-13, 3, -9, -2],
+    [-9, 8, -6, -3],
+    [-13, -6, -8, -2],
+    [5, -9, 8, 10],
+    [2, 7, 3, -9],
+    [-1, -6, -1, -1],
+    [9, 5, 11, -2],
+    [11, -3, 12, -8],
+    [3, 0, 3, 5],
+    [-1, 4, 0, 10],
+    [3, -6, 4, 5],
+    [-13, 0, -10, 5],
+    [5, 8, 12, 11],
+    [8, 9, 9, -6],
+    [7, -4, 8, -12],
+    [-10, 4, -10, 9],
+    [7, 3, 12, 4],
+    [9, -7, 10, -2],
+    [7, 0, 12, -2],
+    [-1, -6, 0, -11],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 随机亮度的方块叠加平滑，角点丰富
+    fn textured_image() -> GrayImage {
+        let mut state: u32 = 2024;
+        let mut next = || {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) % 256
+        };
+        let blocks: Vec<u8> = (0..16 * 16).map(|_| next() as u8).collect();
+        let image = GrayImage::from_fn(256, 256, |x, y| image::Luma([blocks[(y / 16 * 16 + x / 16) as usize]]));
+        gaussian_blur_f32(&image, 1.0)
+    }
+
+    #[test]
+    fn descriptors_are_stable_across_calls() {
+        let image = textured_image();
+        for pattern in [BriefPattern::Learned, BriefPattern::Seeded(7)] {
+            let orb = ORB { pattern, ..ORB::default() };
+            assert_eq!(orb.extract(&image), orb.extract(&image));
+        }
+    }
+
+    #[test]
+    fn seeded_pattern_is_fixed() {
+        let pairs = BriefPattern::Seeded(0).pairs();
+        assert_eq!(pairs.len(), 256);
+        assert!(pairs.iter().flatten().all(|value| value.abs() <= PATTERN_RADIUS));
+        assert_eq!(pairs, BriefPattern::Seeded(0).pairs());
+        assert_ne!(pairs, BriefPattern::Seeded(1).pairs());
+        // 生成方法改变会使已保存的描述子失效
+        assert_eq!(&pairs[..2], &[[-6, -13, -3, -6], [9, -1, 10, -5]]);
+    }
+
+    #[test]
+    fn compute_near_border_at_any_angle() {
+        let image = textured_image();
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        for pattern in [BriefPattern::Learned, BriefPattern::Seeded(7)] {
+            let orb = ORB { pattern, ..ORB::default() };
+            for k in 0..16 {
+                let angle = k as f64 * PI / 8.0;
+                let mut keypoints: Vec<KeyPoint> = [(15.0, 15.0), (19.0, 19.0), (width - 20.0, height - 20.0), (width - 16.0, 40.0)]
+                    .into_iter()
+                    .map(|(x, y)| KeyPoint { angle, ..KeyPoint::new(Vector2::new(x, y)) })
+                    .collect();
+                let descriptors = orb.compute(&image, &mut keypoints);
+                assert_eq!(descriptors.len(), keypoints.len());
+                // 只保留采样点旋转后仍在影像内的特征点
+                assert_eq!(keypoints.len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_across_pyramid_levels() {
+        // 缩小scale_factor²倍后，原图第k层的特征点应出现在第k - 2层
+        let orb = ORB::default();
+        let image = textured_image();
+        let (keypoints, descriptors) = orb.extract(&image);
+        let scale = orb.level_scale(2);
+        let size = (image.width() as f64 / scale).round() as u32;
+        let (small_keypoints, small_descriptors) = orb.extract(&resize(&image, size, size, FilterType::Triangle));
+        let ratio = size as f64 / image.width() as f64;
+
+        let matches = orb.match_descriptors(&descriptors, &small_descriptors);
+        let correct: Vec<(usize, usize)> = matches
+            .iter()
+            .copied()
+            .filter(|&(i, j)| (small_keypoints[j].location - ratio * keypoints[i].location).norm() < 2.0 * orb.level_scale(small_keypoints[j].octave))
+            .collect();
+        assert!(matches.len() * 4 >= keypoints.len(), "{} / {}", matches.len(), keypoints.len());
+        assert!(correct.len() * 10 >= matches.len() * 9, "{} / {}", correct.len(), matches.len());
+        // 原图前两层在缩小后的影像中没有对应的层
+        let deep: Vec<(usize, usize)> = correct.into_iter().filter(|&(i, _)| keypoints[i].octave >= 2).collect();
+        let shifted = deep.iter().filter(|&&(i, j)| keypoints[i].octave == small_keypoints[j].octave + 2).count();
+        assert!(shifted * 10 >= deep.len() * 9, "{} / {}", shifted, deep.len());
+    }
+}
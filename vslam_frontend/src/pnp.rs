@@ -0,0 +1,577 @@
+use crate::ransac::{Estimator, Ransac};
+use nalgebra::{DMatrix, Matrix3, Matrix6, Rotation3, SMatrix, UnitQuaternion, Vector2, Vector3, Vector4, Vector6};
+use vslam_core::camera::Camera;
+use vslam_core::lie::SE3;
+
+/// EPnP在RANSAC中使用的样本数
+const EPNP_SAMPLE_SIZE: usize = 5;
+/// EPnP中对β的高斯牛顿迭代次数
+const EPNP_GAUSS_NEWTON_ITERATIONS: usize = 5;
+
+/// RANSAC中使用的最小解算器
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PnpMethod {
+    P3P,  // Kneip三点法，每个样本至多4个解
+    EPnP, // 四个控制点的EPnP，每个样本5个点
+}
+
+/// 由2D-3D对应估计相机位姿，外层为RANSAC，内点上用高斯牛顿最小化重投影误差
+#[derive(Clone, Copy, Debug)]
+pub struct PnpSolver {
+    pub method: PnpMethod,        // RANSAC中的最小解算器
+    pub threshold: f64,           // 内点的重投影误差阈值，单位像素
+    pub confidence: f64,          // 自适应迭代次数所用的置信度
+    pub max_iterations: usize,    // RANSAC迭代次数上限
+    pub refine_iterations: usize, // 高斯牛顿迭代次数上限
+    pub seed: u64,                // 随机采样的种子
+}
+
+impl Default for PnpSolver {
+    fn default() -> Self {
+        PnpSolver {
+            method: PnpMethod::P3P,
+            threshold: 2.0,
+            confidence: 0.999,
+            max_iterations: 500,
+            refine_iterations: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// PnP的结果
+#[derive(Clone, Debug)]
+pub struct PnpResult {
+    pub pose: SE3,           // Twc，与KeyFrame::pose相同
+    pub inliers: Vec<usize>, // 内点在输入对应中的序号
+}
+
+impl PnpSolver {
+    /// points为世界坐标系下的地图点，pixels为对应的观测像素
+    /// 对应少于最小样本或找不到模型时返回None
+    pub fn solve<C: Camera>(&self, camera: &C, points: &[Vector3<f64>], pixels: &[Vector2<f64>]) -> Option<PnpResult> {
+        let n = points.len().min(pixels.len());
+        let estimator = PnpEstimator {
+            camera,
+            points: &points[..n],
+            pixels: &pixels[..n],
+            bearings: pixels[..n].iter().map(|pixel| camera.unproject(pixel)).collect(),
+            method: self.method,
+        };
+        let threshold = self.threshold * self.threshold;
+        let ransac = Ransac {
+            threshold,
+            confidence: self.confidence,
+            max_iterations: self.max_iterations,
+            seed: self.seed,
+            ..Ransac::default()
+        };
+        let result = ransac.run(&estimator)?;
+
+        let inliers: Vec<usize> = (0..n).filter(|&i| result.inliers[i]).collect();
+        let inlier_points: Vec<Vector3<f64>> = inliers.iter().map(|&i| points[i]).collect();
+        let inlier_pixels: Vec<Vector2<f64>> = inliers.iter().map(|&i| pixels[i]).collect();
+        let pose = refine_pose(camera, &result.model, &inlier_points, &inlier_pixels, self.refine_iterations);
+        let inliers = (0..n).filter(|&i| estimator.residual(&pose, i) <= threshold).collect();
+        Some(PnpResult { pose: pose.inverse(), inliers })
+    }
+}
+
+/// RANSAC中的PnP模型，模型为Tcw，残差为重投影误差的平方
+struct PnpEstimator<'a, C> {
+    camera: &'a C,
+    points: &'a [Vector3<f64>],
+    pixels: &'a [Vector2<f64>],
+    bearings: Vec<Vector3<f64>>,
+    method: PnpMethod,
+}
+
+impl<C: Camera> Estimator for PnpEstimator<'_, C> {
+    type Model = SE3;
+
+    fn sample_size(&self) -> usize {
+        match self.method {
+            PnpMethod::P3P => 3,
+            PnpMethod::EPnP => EPNP_SAMPLE_SIZE,
+        }
+    }
+
+    fn num_data(&self) -> usize {
+        self.points.len()
+    }
+
+    fn fit(&self, sample: &[usize]) -> Vec<SE3> {
+        let points: Vec<Vector3<f64>> = sample.iter().map(|&i| self.points[i]).collect();
+        let bearings: Vec<Vector3<f64>> = sample.iter().map(|&i| self.bearings[i]).collect();
+        match self.method {
+            PnpMethod::P3P => p3p(&points, &bearings),
+            PnpMethod::EPnP => epnp(&points, &bearings).into_iter().collect(),
+        }
+    }
+
+    fn residual(&self, pose: &SE3, index: usize) -> f64 {
+        self.camera
+            .project(&pose.transform_point(&self.points[index]))
+            .map_or(f64::INFINITY, |pixel| (pixel - self.pixels[index]).norm_squared())
+    }
+
+    /// 内点上的EPnP
+    fn refine(&self, _pose: &SE3, inliers: &[usize]) -> Vec<SE3> {
+        let points: Vec<Vector3<f64>> = inliers.iter().map(|&i| self.points[i]).collect();
+        let bearings: Vec<Vector3<f64>> = inliers.iter().map(|&i| self.bearings[i]).collect();
+        epnp(&points, &bearings).into_iter().collect()
+    }
+}
+
+/// Kneip三点法，bearings为相机坐标系下的方位向量，只使用前3个对应
+/// 在由前两个方位向量和前两个点构造的中间坐标系中直接参数化位姿，得到关于cosθ的四次方程，返回至多4个Tcw
+/// 对应不足3个、三点共线或方位向量共面时无解
+pub fn p3p(points: &[Vector3<f64>], bearings: &[Vector3<f64>]) -> Vec<SE3> {
+    if points.len() < 3 || bearings.len() < 3 {
+        return Vec::new();
+    }
+    let mut f: [Vector3<f64>; 3] = std::array::from_fn(|i| bearings[i].normalize());
+    let mut p: [Vector3<f64>; 3] = std::array::from_fn(|i| points[i]);
+    if (p[1] - p[0]).cross(&(p[2] - p[0])).norm() <= 1e-12 * (p[1] - p[0]).norm_squared().max(1.0) {
+        return Vec::new();
+    }
+
+    // 相机端的中间坐标系τ：e1 = f1，e3垂直于f1和f2；交换前两个对应使f3在τ中的z分量为负，θ ∈ [0, π]
+    let camera_frame = |f: &[Vector3<f64>; 3]| -> Option<Matrix3<f64>> {
+        let e3 = f[0].cross(&f[1]).try_normalize(1e-12)?;
+        let e2 = e3.cross(&f[0]);
+        Some(Matrix3::from_rows(&[f[0].transpose(), e2.transpose(), e3.transpose()]))
+    };
+    let Some(mut t) = camera_frame(&f) else {
+        return Vec::new();
+    };
+    let mut f3 = t * f[2];
+    if f3.z > 0.0 {
+        f.swap(0, 1);
+        p.swap(0, 1);
+        let Some(swapped) = camera_frame(&f) else {
+            return Vec::new();
+        };
+        t = swapped;
+        f3 = t * f[2];
+    }
+    if f3.z.abs() < 1e-12 {
+        return Vec::new();
+    }
+
+    // 世界端的中间坐标系η：n1沿P1P2，n3垂直于三点所在平面
+    let n1 = (p[1] - p[0]).normalize();
+    let n3 = n1.cross(&(p[2] - p[0])).normalize();
+    let n2 = n3.cross(&n1);
+    let n = Matrix3::from_rows(&[n1.transpose(), n2.transpose(), n3.transpose()]);
+    let p3 = n * (p[2] - p[0]);
+
+    let d12 = (p[1] - p[0]).norm();
+    let (f1, f2) = (f3.x / f3.z, f3.y / f3.z);
+    let (p1, p2) = (p3.x, p3.y);
+    let cos_beta = f[0].dot(&f[1]);
+    // b = cotβ，β为前两个方位向量的夹角
+    let b = (1.0 / (1.0 - cos_beta * cos_beta) - 1.0).sqrt().copysign(cos_beta);
+
+    let (f1_2, f2_2) = (f1 * f1, f2 * f2);
+    let (p1_2, p1_3, p1_4) = (p1 * p1, p1 * p1 * p1, p1 * p1 * p1 * p1);
+    let (p2_2, p2_3, p2_4) = (p2 * p2, p2 * p2 * p2, p2 * p2 * p2 * p2);
+    let (d12_2, b_2) = (d12 * d12, b * b);
+    // 按升幂排列
+    let quartic = [
+        -2.0 * f2 * p2_2 * f1 * p1 * d12 * b + f2_2 * p2_2 * d12_2 + 2.0 * p1_3 * d12 - p1_2 * d12_2 + f2_2 * p2_2 * p1_2
+            - p1_4 - 2.0 * f2_2 * p2_2 * p1 * d12 + p2_2 * f1_2 * p1_2 + f2_2 * p2_2 * d12_2 * b_2,
+        2.0 * p1_2 * p2 * d12 * b + 2.0 * f2 * p2_3 * f1 * d12 - 2.0 * f2_2 * p2_3 * d12 * b - 2.0 * p1 * p2 * d12_2 * b,
+        -f2_2 * p2_2 * p1_2 - f2_2 * p2_2 * d12_2 * b_2 - f2_2 * p2_2 * d12_2 + f2_2 * p2_4 + p2_4 * f1_2
+            + 2.0 * p1 * p2_2 * d12 + 2.0 * f1 * f2 * p1 * p2_2 * d12 * b - p2_2 * p1_2 * f1_2
+            + 2.0 * p1 * p2_2 * f2_2 * d12 - p2_2 * d12_2 * b_2 - 2.0 * p1_2 * p2_2,
+        2.0 * p2_3 * d12 * b + 2.0 * f2_2 * p2_3 * d12 * b - 2.0 * f2 * p2_3 * f1 * d12,
+        -f2_2 * p2_4 - p2_4 * f1_2 - p2_4,
+    ];
+
+    let mut poses = Vec::new();
+    for cos_theta in real_roots(&quartic) {
+        let cos_theta = cos_theta.clamp(-1.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        // α为P1P2与P1C的夹角，分子分母同乘f2以免除以f2
+        let cot_alpha = (-f1 * p1 - cos_theta * p2 * f2 + d12 * b * f2) / (-f1 * cos_theta * p2 + (p1 - d12) * f2);
+        if !cot_alpha.is_finite() {
+            continue;
+        }
+        let sin_alpha = (1.0 / (cot_alpha * cot_alpha + 1.0)).sqrt();
+        let cos_alpha = (1.0 - sin_alpha * sin_alpha).sqrt().copysign(cot_alpha);
+
+        // η中的相机中心和η到τ的旋转
+        let scale = d12 * (sin_alpha * b + cos_alpha);
+        let center = Vector3::new(cos_alpha * scale, sin_alpha * cos_theta * scale, sin_alpha * sin_theta * scale);
+        #[rustfmt::skip]
+        let rotation = Matrix3::new(
+            -cos_alpha, -sin_alpha * cos_theta, -sin_alpha * sin_theta,
+            sin_alpha, -cos_alpha * cos_theta, -cos_alpha * sin_theta,
+            0.0, -sin_theta, cos_theta,
+        );
+        // Twc的旋转为Nᵀ·Rᵀ·T，平移为P1 + Nᵀ·C
+        let center = p[0] + n.transpose() * center;
+        let rotation_cw = t.transpose() * rotation * n;
+        // 四次方程由平方消元得到，sinθ取负号对应的增根不满足第三个对应
+        if (rotation_cw * (p[2] - center)).normalize().cross(&f[2]).norm() > 1e-6 {
+            continue;
+        }
+        let rotation_cw = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation_cw));
+        poses.push(SE3::new(rotation_cw, -(rotation_cw * center)));
+    }
+    poses
+}
+
+/// EPnP，至少需要4个对应，点不能共面
+/// β的初值分别取控制点零空间维数为4、2、3时的近似解，高斯牛顿优化后取方位误差最小的
+pub fn epnp(points: &[Vector3<f64>], bearings: &[Vector3<f64>]) -> Option<SE3> {
+    let n = points.len().min(bearings.len());
+    if n < 4 {
+        return None;
+    }
+
+    // 控制点：质心和主方向
+    let centroid = points[..n].iter().sum::<Vector3<f64>>() / n as f64;
+    let covariance: Matrix3<f64> = points[..n].iter().map(|p| (p - centroid) * (p - centroid).transpose()).sum();
+    let eigen = covariance.symmetric_eigen();
+    let mut controls = [centroid; 4];
+    for axis in 0..3 {
+        let scale = (eigen.eigenvalues[axis].max(0.0) / n as f64).sqrt();
+        controls[axis + 1] = centroid + eigen.eigenvectors.column(axis) * scale;
+    }
+    let basis = Matrix3::from_columns(&[controls[1] - centroid, controls[2] - centroid, controls[3] - centroid]);
+    let inverse = basis.try_inverse()?;
+    let alphas: Vec<Vector4<f64>> = points[..n]
+        .iter()
+        .map(|p| {
+            let a = inverse * (p - centroid);
+            Vector4::new(1.0 - a.sum(), a.x, a.y, a.z)
+        })
+        .collect();
+
+    // 相机坐标系下的点Σαj·cj与方位向量平行，取与方位向量正交的两个方向作为约束
+    let mut m = DMatrix::zeros(2 * n, 12);
+    for (i, (alpha, bearing)) in alphas.iter().zip(bearings.iter()).enumerate() {
+        let f = bearing.normalize();
+        let helper = if f.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+        let b1 = f.cross(&helper).normalize();
+        let b2 = f.cross(&b1);
+        for j in 0..4 {
+            for c in 0..3 {
+                m[(2 * i, 3 * j + c)] = alpha[j] * b1[c];
+                m[(2 * i + 1, 3 * j + c)] = alpha[j] * b2[c];
+            }
+        }
+    }
+    let mtm = SMatrix::<f64, 12, 12>::from_fn(|r, c| m.column(r).dot(&m.column(c)));
+    let eigen = mtm.symmetric_eigen();
+    let mut order: Vec<usize> = (0..12).collect();
+    order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+    let null: [SMatrix<f64, 12, 1>; 4] = std::array::from_fn(|i| eigen.eigenvectors.column(order[i]).into_owned());
+
+    // 控制点两两距离不变：|Σβi·(vi[a] - vi[b])|² = |ca - cb|²
+    let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+    let mut l = SMatrix::<f64, 6, 10>::zeros();
+    let mut rho = SMatrix::<f64, 6, 1>::zeros();
+    for (row, &(a, b)) in pairs.iter().enumerate() {
+        let dv: [Vector3<f64>; 4] = std::array::from_fn(|i| {
+            Vector3::new(null[i][3 * a] - null[i][3 * b], null[i][3 * a + 1] - null[i][3 * b + 1], null[i][3 * a + 2] - null[i][3 * b + 2])
+        });
+        let mut column = 0;
+        for j in 0..4 {
+            for i in 0..=j {
+                l[(row, column)] = if i == j { dv[i].dot(&dv[j]) } else { 2.0 * dv[i].dot(&dv[j]) };
+                column += 1;
+            }
+        }
+        rho[row] = (controls[a] - controls[b]).norm_squared();
+    }
+
+    // β乘积的顺序为[β00, β01, β11, β02, β12, β22, β03, β13, β23, β33]
+    let approximations: [(&[usize], usize); 3] = [(&[0, 1, 3, 6], 4), (&[0, 1, 2], 2), (&[0, 1, 2, 3, 4, 5], 3)];
+    let mut best: Option<(SE3, f64)> = None;
+    for (columns, dimension) in approximations {
+        let sub = DMatrix::from_fn(6, columns.len(), |r, c| l[(r, columns[c])]);
+        let Ok(products) = sub.svd(true, true).solve(&DMatrix::from_column_slice(6, 1, rho.as_slice()), 1e-12) else {
+            continue;
+        };
+        let mut beta = Vector4::zeros();
+        if dimension == 4 {
+            // β00, β01, β02, β03
+            beta[0] = products[0].abs().sqrt();
+            for i in 1..4 {
+                beta[i] = products[i] / beta[0];
+            }
+        } else {
+            beta[0] = products[0].abs().sqrt();
+            beta[1] = products[2].abs().sqrt() * products[1].signum() * products[0].signum();
+            if dimension == 3 {
+                beta[2] = products[3] / beta[0];
+            }
+        }
+        if !beta.iter().all(|b| b.is_finite()) {
+            continue;
+        }
+        let beta = refine_beta(&l, &rho, beta);
+
+        let camera_controls: [Vector3<f64>; 4] = std::array::from_fn(|j| {
+            (0..4).fold(Vector3::zeros(), |sum, i| sum + beta[i] * Vector3::new(null[i][3 * j], null[i][3 * j + 1], null[i][3 * j + 2]))
+        });
+        let mut camera_points: Vec<Vector3<f64>> = alphas
+            .iter()
+            .map(|alpha| (0..4).fold(Vector3::zeros(), |sum, j| sum + camera_controls[j] * alpha[j]))
+            .collect();
+        if camera_points.iter().zip(bearings.iter()).map(|(p, f)| p.dot(f)).sum::<f64>() < 0.0 {
+            camera_points.iter_mut().for_each(|p| *p = -*p);
+        }
+        let Some(pose) = align(&points[..n], &camera_points) else {
+            continue;
+        };
+        let error: f64 = points[..n]
+            .iter()
+            .zip(bearings.iter())
+            .map(|(p, f)| (pose.transform_point(p).normalize() - f.normalize()).norm_squared())
+            .sum();
+        if best.as_ref().is_none_or(|(_, best_error)| error < *best_error) {
+            best = Some((pose, error));
+        }
+    }
+    best.map(|(pose, _)| pose)
+}
+
+/// 以β为变量最小化Σ(L·b(β) - ρ)²，b(β)为β的两两乘积
+fn refine_beta(l: &SMatrix<f64, 6, 10>, rho: &SMatrix<f64, 6, 1>, mut beta: Vector4<f64>) -> Vector4<f64> {
+    for _ in 0..EPNP_GAUSS_NEWTON_ITERATIONS {
+        let mut products = SMatrix::<f64, 10, 1>::zeros();
+        let mut jacobian = SMatrix::<f64, 10, 4>::zeros();
+        let mut column = 0;
+        for j in 0..4 {
+            for i in 0..=j {
+                products[column] = beta[i] * beta[j];
+                jacobian[(column, i)] += beta[j];
+                jacobian[(column, j)] += beta[i];
+                column += 1;
+            }
+        }
+        let residual = l * products - rho;
+        let j = l * jacobian;
+        let Some(delta) = (j.transpose() * j).try_inverse().map(|inverse| inverse * j.transpose() * residual) else {
+            break;
+        };
+        beta -= delta;
+    }
+    beta
+}
+
+/// 由对应的世界坐标和相机坐标求刚体变换Tcw（Kabsch）
+fn align(world: &[Vector3<f64>], camera: &[Vector3<f64>]) -> Option<SE3> {
+    let n = world.len().min(camera.len());
+    let world_centroid = world[..n].iter().sum::<Vector3<f64>>() / n as f64;
+    let camera_centroid = camera[..n].iter().sum::<Vector3<f64>>() / n as f64;
+    let covariance: Matrix3<f64> = world[..n]
+        .iter()
+        .zip(camera[..n].iter())
+        .map(|(w, c)| (c - camera_centroid) * (w - world_centroid).transpose())
+        .sum();
+    let svd = covariance.svd(true, true);
+    let (u, v_t) = (svd.u?, svd.v_t?);
+    let sign = (u * v_t).determinant().signum();
+    let rotation = u * Matrix3::from_diagonal(&Vector3::new(1.0, 1.0, sign)) * v_t;
+    if !rotation.iter().all(|r| r.is_finite()) {
+        return None;
+    }
+    let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+    Some(SE3::new(rotation, camera_centroid - rotation * world_centroid))
+}
+
+/// 高斯牛顿最小化重投影误差，pose为Tcw，使用Tcw的左扰动
+/// 代价不再下降时停止
+pub fn refine_pose<C: Camera>(camera: &C, pose: &SE3, points: &[Vector3<f64>], pixels: &[Vector2<f64>], iterations: usize) -> SE3 {
+    let cost = |pose: &SE3| -> f64 {
+        points
+            .iter()
+            .zip(pixels.iter())
+            .map(|(point, pixel)| {
+                camera.project(&pose.transform_point(point)).map_or(f64::INFINITY, |p| (p - pixel).norm_squared())
+            })
+            .sum()
+    };
+
+    let mut pose = *pose;
+    let mut current = cost(&pose);
+    for _ in 0..iterations {
+        let mut hessian = Matrix6::zeros();
+        let mut gradient = Vector6::zeros();
+        for (point, pixel) in points.iter().zip(pixels.iter()) {
+            let camera_point = pose.transform_point(point);
+            let Some(projected) = camera.project(&camera_point) else {
+                continue;
+            };
+            let jacobian = camera.pose_jacobian(&camera_point);
+            hessian += jacobian.transpose() * jacobian;
+            gradient += jacobian.transpose() * (projected - pixel);
+        }
+        let Some(delta) = hessian.cholesky().map(|cholesky| -cholesky.solve(&gradient)) else {
+            break;
+        };
+        let candidate = pose.retract_left(&delta);
+        let candidate_cost = cost(&candidate);
+        if candidate_cost >= current {
+            break;
+        }
+        pose = candidate;
+        current = candidate_cost;
+        if delta.norm() < 1e-10 {
+            break;
+        }
+    }
+    pose
+}
+
+/// 升幂系数的多项式求值
+fn poly_eval(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |value, c| value * x + c)
+}
+
+/// 多项式的实根，由伴随矩阵的特征值求得，再用牛顿法修正
+fn real_roots(coefficients: &[f64]) -> Vec<f64> {
+    let scale = coefficients.iter().fold(0.0f64, |max, c| max.max(c.abs()));
+    let degree = coefficients.iter().rposition(|c| c.abs() > 1e-12 * scale).unwrap_or(0);
+    if degree == 0 {
+        return Vec::new();
+    }
+    let leading = coefficients[degree];
+    let mut companion = DMatrix::zeros(degree, degree);
+    for i in 0..degree {
+        companion[(0, i)] = -coefficients[degree - 1 - i] / leading;
+        if i + 1 < degree {
+            companion[(i + 1, i)] = 1.0;
+        }
+    }
+    let derivative: Vec<f64> = coefficients[1..=degree].iter().enumerate().map(|(i, c)| (i + 1) as f64 * c).collect();
+    companion
+        .complex_eigenvalues()
+        .iter()
+        .filter(|root| root.im.abs() <= 1e-6 * (1.0 + root.re.abs()))
+        .map(|root| {
+            let mut x = root.re;
+            for _ in 0..3 {
+                let slope = poly_eval(&derivative, x);
+                if slope.abs() < 1e-15 {
+                    break;
+                }
+                x -= poly_eval(&coefficients[..=degree], x) / slope;
+            }
+            x
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+    use vslam_core::camera::PinholeCamera;
+
+    /// 相机前方的随机点和真实的Tcw
+    fn scene(count: usize, seed: u64) -> (Vec<Vector3<f64>>, SE3) {
+        let mut state = seed;
+        let mut uniform = |low: f64, high: f64| low + (high - low) * (split_mix64(&mut state) % 100000) as f64 / 100000.0;
+        let pose = SE3::new(UnitQuaternion::from_euler_angles(0.1, -0.2, 0.3), Vector3::new(0.3, -0.2, 0.5));
+        let camera_to_world = pose.inverse();
+        let points = (0..count)
+            .map(|_| {
+                let camera_point = Vector3::new(uniform(-2.0, 2.0), uniform(-1.5, 1.5), uniform(3.0, 8.0));
+                camera_to_world.transform_point(&camera_point)
+            })
+            .collect();
+        (points, pose)
+    }
+
+    fn pose_error(a: &SE3, b: &SE3) -> f64 {
+        a.inverse().compose(b).log().norm()
+    }
+
+    #[test]
+    fn minimal_solvers_recover_exact_pose() {
+        let (points, pose) = scene(20, 1);
+        let bearings: Vec<Vector3<f64>> = points.iter().map(|p| pose.transform_point(p).normalize()).collect();
+
+        let solutions = p3p(&points, &bearings);
+        assert!(!solutions.is_empty() && solutions.len() <= 4);
+        let best = solutions.iter().map(|solution| pose_error(solution, &pose)).fold(f64::INFINITY, f64::min);
+        assert!(best < 1e-6, "{}", best);
+
+        assert!(p3p(&points[..2], &bearings[..2]).is_empty());
+
+        let estimated = epnp(&points, &bearings).unwrap();
+        assert!(pose_error(&estimated, &pose) < 1e-6, "{:?}", estimated);
+    }
+
+    #[test]
+    fn robust_solver_rejects_outliers() {
+        let camera = PinholeCamera::new(450.0, 450.0, 320.0, 240.0, 640, 480);
+        let (points, pose) = scene(150, 2);
+        let mut state = 3;
+        let mut noise = || ((split_mix64(&mut state) % 1000) as f64 / 1000.0 - 0.5) * 0.8;
+        let pixels: Vec<Vector2<f64>> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let pixel = camera.project(&pose.transform_point(point)).unwrap() + Vector2::new(noise(), noise());
+                // 每5个点中1个为外点
+                if i % 5 == 0 { pixel + Vector2::new(40.0, -30.0) } else { pixel }
+            })
+            .collect();
+
+        for method in [PnpMethod::P3P, PnpMethod::EPnP] {
+            let result = PnpSolver { method, ..PnpSolver::default() }.solve(&camera, &points, &pixels).unwrap();
+            let error = pose_error(&result.pose.inverse(), &pose);
+            assert!(error < 0.01, "{:?} {}", method, error);
+            assert!(result.inliers.iter().all(|i| i % 5 != 0));
+            assert_eq!(result.inliers.len(), 120, "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn p3p_tolerates_noise_and_near_degenerate_points() {
+        let camera = PinholeCamera::new(450.0, 450.0, 320.0, 240.0, 640, 480);
+        let mut state = 4;
+        let mut noise = || (split_mix64(&mut state) % 1000) as f64 / 1000.0 - 0.5;
+        let closest = |points: &[Vector3<f64>], bearings: &[Vector3<f64>], pose: &SE3| {
+            p3p(points, bearings).iter().map(|solution| pose_error(solution, pose)).fold(f64::INFINITY, f64::min)
+        };
+
+        // 像素上加±0.5的噪声，三个点的解对噪声敏感
+        // 只要求每个解都精确符合带噪声的观测（点在方位向量所在直线上），且多数接近真值
+        let mut errors: Vec<f64> = (0..50)
+            .map(|seed| {
+                let (points, pose) = scene(3, seed);
+                let bearings: Vec<Vector3<f64>> = points
+                    .iter()
+                    .map(|p| camera.unproject(&(camera.project(&pose.transform_point(p)).unwrap() + Vector2::new(noise(), noise()))))
+                    .collect();
+                for solution in p3p(&points, &bearings) {
+                    for (point, bearing) in points.iter().zip(bearings.iter()) {
+                        let residual = solution.transform_point(point).normalize().cross(bearing).norm();
+                        assert!(residual < 1e-9, "{} {}", seed, residual);
+                    }
+                }
+                closest(&points, &bearings, &pose)
+            })
+            .collect();
+        errors.sort_by(f64::total_cmp);
+        assert!(errors[25] < 0.05, "{:?}", errors);
+
+        // 第三个点与前两个点的连线相距1cm
+        for seed in 0..50 {
+            let (mut points, pose) = scene(3, seed);
+            let offset = (points[1] - points[0]).cross(&Vector3::z()).normalize() * 1e-2;
+            points[2] = points[0].lerp(&points[1], 0.3) + offset;
+            let bearings: Vec<Vector3<f64>> = points.iter().map(|p| pose.transform_point(p).normalize()).collect();
+            let error = closest(&points, &bearings, &pose);
+            assert!(error < 1e-8, "{} {}", seed, error);
+        }
+    }
+}
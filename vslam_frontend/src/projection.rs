@@ -0,0 +1,325 @@
+use crate::hamming::hamming_distance;
+use nalgebra::Vector2;
+use vslam_core::camera::Camera;
+use vslam_core::feature::KeyPoint;
+use vslam_core::lie::SE3;
+use vslam_core::map::Map;
+
+/// 特征点网格索引，按位置分桶，用于查询窗口内的特征点
+pub struct KeyPointGrid {
+    cell_size: f64,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>, // 行优先，每格中的特征点序号
+}
+
+impl KeyPointGrid {
+    /// 覆盖width x height影像的网格，影像外的特征点被忽略
+    pub fn new(keypoints: &[KeyPoint], width: u32, height: u32, cell_size: f64) -> Self {
+        let cell_size = cell_size.max(1.0);
+        let columns = ((width as f64 / cell_size).ceil() as usize).max(1);
+        let rows = ((height as f64 / cell_size).ceil() as usize).max(1);
+        let mut grid = KeyPointGrid { cell_size, columns, rows, cells: vec![Vec::new(); columns * rows] };
+        for (index, keypoint) in keypoints.iter().enumerate() {
+            let location = keypoint.location;
+            if location.x < 0.0 || location.y < 0.0 {
+                continue;
+            }
+            let (column, row) = grid.cell_of(&location);
+            if column < columns && row < rows {
+                grid.cells[row * columns + column].push(index);
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, location: &Vector2<f64>) -> (usize, usize) {
+        ((location.x / self.cell_size) as usize, (location.y / self.cell_size) as usize)
+    }
+
+    /// 以center为中心、边长2·radius的方形窗口内的特征点，octave在[min_octave, max_octave]内
+    pub fn features_in_area(
+        &self,
+        keypoints: &[KeyPoint],
+        center: &Vector2<f64>,
+        radius: f64,
+        min_octave: usize,
+        max_octave: usize,
+    ) -> Vec<usize> {
+        let min_column = ((center.x - radius) / self.cell_size).floor().max(0.0) as usize;
+        let min_row = ((center.y - radius) / self.cell_size).floor().max(0.0) as usize;
+        let max_column = ((center.x + radius) / self.cell_size).floor();
+        let max_row = ((center.y + radius) / self.cell_size).floor();
+        if max_column < 0.0 || max_row < 0.0 {
+            return Vec::new();
+        }
+        let max_column = (max_column as usize).min(self.columns - 1);
+        let max_row = (max_row as usize).min(self.rows - 1);
+
+        let mut indices = Vec::new();
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                for &index in &self.cells[row * self.columns + column] {
+                    let keypoint = &keypoints[index];
+                    let offset = keypoint.location - center;
+                    if offset.x.abs() <= radius
+                        && offset.y.abs() <= radius
+                        && keypoint.octave >= min_octave
+                        && keypoint.octave <= max_octave
+                    {
+                        indices.push(index);
+                    }
+                }
+            }
+        }
+        indices
+    }
+}
+
+/// 地图点与当前帧特征的匹配
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProjectionMatch {
+    pub map_point: usize, // 地图点id
+    pub index: usize,     // 当前帧特征序号
+    pub distance: f64,    // 描述子汉明距离
+}
+
+/// 投影匹配，与ORB-SLAM跟踪局部地图相同
+/// 用预测位姿把局部地图点投影到当前帧，只在投影点附近的窗口内搜索特征，窗口随预测的金字塔层放大
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectionMatcher {
+    pub radius: f64,                  // 第0层的搜索窗口半径，单位像素
+    pub scale_factor: f64,            // 金字塔相邻层的缩放比例，与ORB相同
+    pub n_levels: usize,              // 金字塔层数，与ORB相同
+    pub max_distance: f64,            // 描述子距离上限
+    pub ratio_threshold: Option<f64>, // 窗口内最近邻与次近邻的比值测试阈值
+    pub min_viewing_cos: f64,         // 观测方向与平均观测方向夹角余弦的下限
+    pub cell_size: f64,               // 特征点网格边长，单位像素
+}
+
+impl Default for ProjectionMatcher {
+    fn default() -> Self {
+        ProjectionMatcher {
+            radius: 4.0,
+            scale_factor: 1.2,
+            n_levels: 8,
+            max_distance: 100.0,
+            ratio_threshold: Some(0.8),
+            min_viewing_cos: 0.5,
+            cell_size: 10.0,
+        }
+    }
+}
+
+impl ProjectionMatcher {
+    /// 把local_map_points中的地图点投影到位姿为pose（Twc）的当前帧中匹配
+    /// 每个特征最多与一个地图点匹配，冲突时保留距离最小的
+    pub fn search_by_projection<const N: usize, C: Camera>(
+        &self,
+        map: &Map<[u64; N]>,
+        local_map_points: &[usize],
+        camera: &C,
+        pose: &SE3,
+        keypoints: &[KeyPoint],
+        descriptors: &[[u64; N]],
+    ) -> Vec<ProjectionMatch> {
+        let grid = KeyPointGrid::new(keypoints, camera.width(), camera.height(), self.cell_size);
+        let world_to_camera = pose.inverse();
+        let center = pose.translation();
+        let mut best_for_feature: Vec<Option<ProjectionMatch>> = vec![None; keypoints.len()];
+
+        for &id in local_map_points {
+            let Some(map_point) = map.map_point(id) else {
+                continue;
+            };
+            let position = map_point.position();
+            let Some(pixel) = camera.project(&world_to_camera.transform_point(position)) else {
+                continue;
+            };
+            if !camera.is_in_image(&pixel) {
+                continue;
+            }
+            let direction = position - center;
+            let distance = direction.norm();
+            if distance <= 0.0 || !map_point.is_in_depth_range(distance) {
+                continue;
+            }
+            let normal = map_point.normal();
+            if normal.norm() > 0.0 && normal.dot(&direction) / distance < self.min_viewing_cos {
+                continue;
+            }
+
+            let level = self.predict_level(map_point.max_distance(), distance);
+            let radius = self.radius * self.scale_factor.powi(level as i32);
+            let descriptor = map_point.descriptor();
+            let mut best: Option<(usize, u32)> = None;
+            let mut second = u32::MAX;
+            for index in grid.features_in_area(keypoints, &pixel, radius, level.saturating_sub(1), level) {
+                let distance = hamming_distance(descriptor, &descriptors[index]);
+                if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                    second = best.map_or(u32::MAX, |(_, best_distance)| best_distance);
+                    best = Some((index, distance));
+                } else if distance < second {
+                    second = distance;
+                }
+            }
+
+            let Some((index, best_distance)) = best else {
+                continue;
+            };
+            if best_distance as f64 > self.max_distance {
+                continue;
+            }
+            if let Some(ratio) = self.ratio_threshold {
+                if second != u32::MAX && best_distance as f64 > ratio * second as f64 {
+                    continue;
+                }
+            }
+            let candidate = ProjectionMatch { map_point: id, index, distance: best_distance as f64 };
+            let slot = &mut best_for_feature[index];
+            if slot.is_none_or(|existing| candidate.distance < existing.distance) {
+                *slot = Some(candidate);
+            }
+        }
+
+        best_for_feature.into_iter().flatten().collect()
+    }
+
+    /// 与ORB-SLAM相同，把地图点的有效距离设为尺度不变的范围
+    /// 最早观测到该点的关键帧中，特征在第k层、距离为d时，max_distance = d·scale_factor^k，
+    /// min_distance = max_distance / scale_factor^(n_levels - 1)
+    /// 会覆盖Map::update_normal_and_depth得到的距离范围，没有观测时不变
+    pub fn update_depth_range<D>(&self, map: &mut Map<D>, id: usize) {
+        let Some(map_point) = map.map_point(id) else {
+            return;
+        };
+        let Some((&keyframe_id, &index)) = map_point.observations().iter().next() else {
+            return;
+        };
+        let Some(keyframe) = map.keyframe(keyframe_id) else {
+            return;
+        };
+        let Some(keypoint) = keyframe.keypoint(index) else {
+            return;
+        };
+        let distance = (map_point.position() - keyframe.camera_center()).norm();
+        let max_distance = distance * self.scale_factor.powi(keypoint.octave as i32);
+        let min_distance = max_distance / self.scale_factor.powi(self.n_levels.saturating_sub(1) as i32);
+        if let Some(map_point) = map.map_point_mut(id) {
+            map_point.set_depth_range(min_distance, max_distance);
+        }
+    }
+
+    /// 由地图点的最大有效距离预测当前距离下的金字塔层，与ORB-SLAM的PredictScale相同
+    /// 距离为max_distance时在第0层，距离每缩小为1/scale_factor升高一层
+    /// 没有设置距离范围时为第0层
+    fn predict_level(&self, max_distance: f64, distance: f64) -> usize {
+        if !max_distance.is_finite() {
+            return 0;
+        }
+        let level = ((max_distance / distance).ln() / self.scale_factor.ln()).ceil();
+        level.clamp(0.0, self.n_levels.saturating_sub(1) as f64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+    use nalgebra::{UnitQuaternion, Vector3};
+    use vslam_core::camera::PinholeCamera;
+    use vslam_core::keyframe::KeyFrame;
+
+    #[test]
+    fn grid_agrees_with_brute_force() {
+        let mut state = 5;
+        let keypoints: Vec<KeyPoint> = (0..500)
+            .map(|_| {
+                let x = (split_mix64(&mut state) % 6400) as f64 / 10.0;
+                let y = (split_mix64(&mut state) % 4800) as f64 / 10.0;
+                KeyPoint { octave: (split_mix64(&mut state) % 4) as usize, ..KeyPoint::new(Vector2::new(x, y)) }
+            })
+            .collect();
+        let grid = KeyPointGrid::new(&keypoints, 640, 480, 10.0);
+        for (center, radius) in [(Vector2::new(320.0, 240.0), 30.0), (Vector2::new(2.0, 470.0), 15.0), (Vector2::new(-5.0, -5.0), 8.0)] {
+            let mut found = grid.features_in_area(&keypoints, &center, radius, 1, 2);
+            found.sort();
+            let expected: Vec<usize> = (0..keypoints.len())
+                .filter(|&i| {
+                    let offset = keypoints[i].location - center;
+                    offset.x.abs() <= radius && offset.y.abs() <= radius && (1..=2).contains(&keypoints[i].octave)
+                })
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn matches_projected_map_points() {
+        let camera = PinholeCamera::new(400.0, 400.0, 320.0, 240.0, 640, 480);
+        let mut state = 11;
+        let mut uniform = |low: f64, high: f64| low + (high - low) * (split_mix64(&mut state) % 10000) as f64 / 10000.0;
+        let points: Vec<Vector3<f64>> = (0..200)
+            .map(|_| Vector3::new(uniform(-3.0, 3.0), uniform(-2.0, 2.0), uniform(4.0, 8.0)))
+            .collect();
+        let mut state = 12;
+        let descriptors: Vec<[u64; 4]> = (0..points.len()).map(|_| [0; 4].map(|_: u64| split_mix64(&mut state))).collect();
+
+        // 参考关键帧位于原点，地图点都由第0层特征观测
+        let reference_keypoints: Vec<KeyPoint> = points
+            .iter()
+            .map(|point| KeyPoint::new(camera.project(point).unwrap()))
+            .collect();
+        let mut map = Map::new();
+        map.insert_keyframe(KeyFrame::new(0, 0.0, String::new(), SE3::identity(), reference_keypoints, descriptors.clone()).unwrap());
+        let ids: Vec<usize> = points
+            .iter()
+            .zip(descriptors.iter())
+            .enumerate()
+            .map(|(index, (point, descriptor))| {
+                let id = map.insert_map_point(*point, *descriptor);
+                map.add_observation(id, 0, index);
+                map.update_normal_and_depth(id);
+                ProjectionMatcher::default().update_depth_range(&mut map, id);
+                id
+            })
+            .collect();
+
+        // 当前帧：真实位姿下的投影加少量噪声，描述子翻转若干位，特征顺序打乱
+        let pose = SE3::new(UnitQuaternion::from_euler_angles(0.02, -0.03, 0.01), Vector3::new(0.2, -0.1, 0.3));
+        let world_to_camera = pose.inverse();
+        let mut truth = Vec::new();
+        let mut keypoints = Vec::new();
+        let mut frame_descriptors = Vec::new();
+        for (i, (point, descriptor)) in points.iter().zip(descriptors.iter()).enumerate().rev() {
+            let Some(pixel) = camera.project(&world_to_camera.transform_point(point)).filter(|p| camera.is_in_image(p)) else {
+                continue;
+            };
+            truth.push((ids[i], keypoints.len()));
+            keypoints.push(KeyPoint::new(pixel + Vector2::new(0.5, -0.5)));
+            frame_descriptors.push([descriptor[0] ^ 0b1011, descriptor[1], descriptor[2] ^ 1 << 40, descriptor[3]]);
+        }
+        assert!(truth.len() > 100);
+
+        // 预测位姿略有偏差
+        let predicted = pose.compose(&SE3::new(UnitQuaternion::identity(), Vector3::new(0.005, 0.0, 0.0)));
+        let matches = ProjectionMatcher::default().search_by_projection(&map, &ids, &camera, &predicted, &keypoints, &frame_descriptors);
+        assert!(matches.len() * 10 >= truth.len() * 9, "{} / {}", matches.len(), truth.len());
+        for m in &matches {
+            assert!(truth.contains(&(m.map_point, m.index)), "{:?}", m);
+            assert_eq!(m.distance, 4.0);
+        }
+
+        // 超出有效距离的地图点不参与匹配
+        let outside = matches[0];
+        let distance = (map.map_point(outside.map_point).unwrap().position() - predicted.translation()).norm();
+        map.map_point_mut(outside.map_point).unwrap().set_depth_range(0.0, 0.9 * distance);
+        let matches = ProjectionMatcher::default().search_by_projection(&map, &ids, &camera, &predicted, &keypoints, &frame_descriptors);
+        assert!(matches.iter().all(|m| m.map_point != outside.map_point));
+
+        // 预测位姿偏差远大于窗口时找不到匹配
+        let far = pose.compose(&SE3::new(UnitQuaternion::identity(), Vector3::new(0.5, 0.0, 0.0)));
+        let matches = ProjectionMatcher::default().search_by_projection(&map, &ids, &camera, &far, &keypoints, &frame_descriptors);
+        assert!(matches.len() * 10 < truth.len());
+    }
+}
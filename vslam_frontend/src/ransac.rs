@@ -0,0 +1,337 @@
+use crate::rng::split_mix64;
+
+/// PROSAC中T_N的取值，与原论文相同
+const PROSAC_MAX_SAMPLES: f64 = 200000.0;
+
+/// 鲁棒估计中的模型，由最小解算器和残差函数组成
+/// 数据以序号0..num_data()表示，具体的数据由实现者持有
+pub trait Estimator {
+    type Model: Clone;
+
+    /// 最小样本的数据个数
+    fn sample_size(&self) -> usize;
+
+    /// 数据总数
+    fn num_data(&self) -> usize;
+
+    /// 由最小样本估计模型，可能有多个解或无解
+    fn fit(&self, sample: &[usize]) -> Vec<Self::Model>;
+
+    /// 第index个数据对模型的残差
+    fn residual(&self, model: &Self::Model, index: usize) -> f64;
+
+    /// 第index个数据的得分，None为外点
+    /// 默认为MSAC的截断二次得分：残差不超过threshold时得threshold - 残差
+    fn score(&self, model: &Self::Model, index: usize, threshold: f64) -> Option<f64> {
+        let residual = self.residual(model, index);
+        (residual <= threshold).then_some(threshold - residual)
+    }
+
+    /// 由全部内点做非最小拟合，用于LO-RANSAC的局部优化
+    /// 默认不支持，返回空
+    fn refine(&self, _model: &Self::Model, _inliers: &[usize]) -> Vec<Self::Model> {
+        Vec::new()
+    }
+}
+
+/// RANSAC参数，同时用于PROSAC
+#[derive(Clone, Copy, Debug)]
+pub struct Ransac {
+    pub threshold: f64,           // 内点的残差阈值，与Estimator::residual的单位相同
+    pub confidence: f64,          // 自适应迭代次数所用的置信度
+    pub min_iterations: usize,    // 迭代次数下限
+    pub max_iterations: usize,    // 迭代次数上限
+    pub local_optimization: bool, // 是否在找到更好的模型时做局部优化（LO-RANSAC）
+    pub local_iterations: usize,  // 局部优化中迭代重拟合的次数上限
+    pub seed: u64,                // 随机采样的种子，相同种子的结果可复现
+}
+
+impl Default for Ransac {
+    fn default() -> Self {
+        Ransac {
+            threshold: 1.0,
+            confidence: 0.999,
+            min_iterations: 0,
+            max_iterations: 1000,
+            local_optimization: true,
+            local_iterations: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// 鲁棒估计的结果
+#[derive(Clone, Debug)]
+pub struct RansacResult<M> {
+    pub model: M,
+    pub inliers: Vec<bool>, // 与数据一一对应
+    pub num_inliers: usize,
+    pub score: f64,         // 所有内点得分之和
+    pub iterations: usize,  // 实际采样次数
+}
+
+/// 当前最好的模型
+struct Best<M> {
+    model: M,
+    score: f64,
+    num_inliers: usize,
+}
+
+impl Ransac {
+    /// 均匀采样的RANSAC，数据少于最小样本或找不到模型时返回None
+    pub fn run<E: Estimator>(&self, estimator: &E) -> Option<RansacResult<E::Model>> {
+        let n = estimator.num_data();
+        let m = estimator.sample_size();
+        self.run_with(estimator, |state, _| random_sample(state, n, m))
+    }
+
+    /// PROSAC，order为按匹配质量从高到低排列的数据序号
+    /// 先在质量最高的少量数据中采样，随迭代逐渐扩大到全部数据
+    pub fn run_prosac<E: Estimator>(&self, estimator: &E, order: &[usize]) -> Option<RansacResult<E::Model>> {
+        let n = order.len().min(estimator.num_data());
+        let m = estimator.sample_size();
+        if n < m {
+            return None;
+        }
+
+        // T_n为在前n个数据中期望的采样次数，T'_n为切换到前n+1个数据的迭代序号
+        let mut size = m;
+        let mut expected = PROSAC_MAX_SAMPLES;
+        for i in 0..m {
+            expected *= (m - i) as f64 / (n - i) as f64;
+        }
+        let mut switch = 1.0;
+        self.run_with(estimator, |state, iteration| {
+            if iteration as f64 >= switch && size < n {
+                let next = expected * (size + 1) as f64 / (size + 1 - m) as f64;
+                switch += (next - expected).ceil();
+                expected = next;
+                size += 1;
+            }
+            let sample = if switch < iteration as f64 || size == m {
+                random_sample(state, size, m)
+            } else {
+                // 必须包含第size个数据，其余从前size-1个中选取
+                let mut sample = random_sample(state, size - 1, m - 1);
+                sample.push(size - 1);
+                sample
+            };
+            sample.into_iter().map(|k| order[k]).collect()
+        })
+    }
+
+    fn run_with<E: Estimator>(
+        &self,
+        estimator: &E,
+        mut sampler: impl FnMut(&mut u64, usize) -> Vec<usize>,
+    ) -> Option<RansacResult<E::Model>> {
+        let n = estimator.num_data();
+        let m = estimator.sample_size();
+        if n < m || m == 0 {
+            return None;
+        }
+
+        let mut state = self.seed;
+        let mut best: Option<Best<E::Model>> = None;
+        let mut required = self.max_iterations;
+        let mut iteration = 0;
+        while iteration < self.max_iterations && (iteration < self.min_iterations || iteration < required) {
+            iteration += 1;
+            let sample = sampler(&mut state, iteration);
+            for model in estimator.fit(&sample) {
+                let (score, num_inliers) = self.evaluate(estimator, &model);
+                if best.as_ref().is_some_and(|best| score <= best.score) {
+                    continue;
+                }
+                let mut candidate = Best { model, score, num_inliers };
+                if self.local_optimization {
+                    candidate = self.local_optimize(estimator, candidate);
+                }
+                required = adaptive_iterations(candidate.num_inliers as f64 / n as f64, m, self.confidence);
+                best = Some(candidate);
+            }
+        }
+
+        let best = best?;
+        let inliers: Vec<bool> = (0..n)
+            .map(|index| estimator.score(&best.model, index, self.threshold).is_some())
+            .collect();
+        Some(RansacResult {
+            model: best.model,
+            inliers,
+            num_inliers: best.num_inliers,
+            score: best.score,
+            iterations: iteration,
+        })
+    }
+
+    /// 模型的得分和内点数
+    fn evaluate<E: Estimator>(&self, estimator: &E, model: &E::Model) -> (f64, usize) {
+        (0..estimator.num_data())
+            .filter_map(|index| estimator.score(model, index, self.threshold))
+            .fold((0.0, 0), |(score, count), s| (score + s, count + 1))
+    }
+
+    /// 用当前内点反复重拟合，得分不再提高时停止
+    fn local_optimize<E: Estimator>(&self, estimator: &E, mut best: Best<E::Model>) -> Best<E::Model> {
+        for _ in 0..self.local_iterations {
+            let inliers: Vec<usize> = (0..estimator.num_data())
+                .filter(|&index| estimator.score(&best.model, index, self.threshold).is_some())
+                .collect();
+            if inliers.len() <= estimator.sample_size() {
+                break;
+            }
+            let mut improved = false;
+            for model in estimator.refine(&best.model, &inliers) {
+                let (score, num_inliers) = self.evaluate(estimator, &model);
+                if score > best.score {
+                    best = Best { model, score, num_inliers };
+                    improved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+        best
+    }
+}
+
+/// 从0..n中不重复地随机选取count个
+pub fn random_sample(state: &mut u64, n: usize, count: usize) -> Vec<usize> {
+    let mut sample = Vec::with_capacity(count);
+    while sample.len() < count.min(n) {
+        let index = (split_mix64(state) % n as u64) as usize;
+        if !sample.contains(&index) {
+            sample.push(index);
+        }
+    }
+    sample
+}
+
+/// 内点率为inlier_ratio时，以confidence的概率至少采到一次全内点样本所需的迭代次数
+pub fn adaptive_iterations(inlier_ratio: f64, sample_size: usize, confidence: f64) -> usize {
+    let all_inliers = inlier_ratio.powi(sample_size as i32);
+    if all_inliers <= 0.0 {
+        return usize::MAX;
+    }
+    if all_inliers >= 1.0 {
+        return 1;
+    }
+    let iterations = (1.0 - confidence).ln() / (1.0 - all_inliers).ln();
+    if iterations.is_finite() {
+        iterations.ceil().max(1.0) as usize
+    } else {
+        usize::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    /// 拟合直线ax + by + c = 0，(a, b)为单位向量
+    struct LineEstimator {
+        points: Vec<Vector2<f64>>,
+    }
+
+    impl Estimator for LineEstimator {
+        type Model = (Vector2<f64>, f64);
+
+        fn sample_size(&self) -> usize {
+            2
+        }
+
+        fn num_data(&self) -> usize {
+            self.points.len()
+        }
+
+        fn fit(&self, sample: &[usize]) -> Vec<Self::Model> {
+            let (p, q) = (self.points[sample[0]], self.points[sample[1]]);
+            let direction = q - p;
+            if direction.norm() == 0.0 {
+                return Vec::new();
+            }
+            let normal = Vector2::new(-direction.y, direction.x).normalize();
+            vec![(normal, -normal.dot(&p))]
+        }
+
+        fn residual(&self, model: &Self::Model, index: usize) -> f64 {
+            (model.0.dot(&self.points[index]) + model.1).powi(2)
+        }
+
+        /// 内点的总体最小二乘
+        fn refine(&self, _model: &Self::Model, inliers: &[usize]) -> Vec<Self::Model> {
+            let centroid = inliers.iter().map(|&i| self.points[i]).sum::<Vector2<f64>>() / inliers.len() as f64;
+            let covariance = inliers
+                .iter()
+                .map(|&i| (self.points[i] - centroid) * (self.points[i] - centroid).transpose())
+                .sum::<nalgebra::Matrix2<f64>>();
+            let eigen = covariance.symmetric_eigen();
+            let normal: Vector2<f64> = eigen.eigenvectors.column(eigen.eigenvalues.imin()).into_owned();
+            vec![(normal, -normal.dot(&centroid))]
+        }
+    }
+
+    /// y = 0.5x + 2上的点加少量噪声，每3个点中1个为外点
+    fn line_points(seed: u64) -> LineEstimator {
+        let mut state = seed;
+        let mut uniform = |low: f64, high: f64| low + (high - low) * (split_mix64(&mut state) % 100000) as f64 / 100000.0;
+        let points = (0..300)
+            .map(|i| {
+                let x = uniform(-50.0, 50.0);
+                if i % 3 == 0 {
+                    Vector2::new(x, uniform(-50.0, 50.0))
+                } else {
+                    Vector2::new(x, 0.5 * x + 2.0 + uniform(-0.2, 0.2))
+                }
+            })
+            .collect();
+        LineEstimator { points }
+    }
+
+    fn check_line(result: &RansacResult<(Vector2<f64>, f64)>) {
+        let (normal, offset) = result.model;
+        let slope = -normal.x / normal.y;
+        let intercept = -offset / normal.y;
+        assert!((slope - 0.5).abs() < 0.01 && (intercept - 2.0).abs() < 0.1, "{} {}", slope, intercept);
+        assert!(result.num_inliers >= 195, "{}", result.num_inliers);
+    }
+
+    #[test]
+    fn ransac_fits_line_reproducibly() {
+        let estimator = line_points(1);
+        for local_optimization in [false, true] {
+            let ransac = Ransac { threshold: 0.1, local_optimization, ..Ransac::default() };
+            let result = ransac.run(&estimator).unwrap();
+            check_line(&result);
+            assert_eq!(result.inliers.iter().filter(|&&inlier| inlier).count(), result.num_inliers);
+            assert!(result.iterations < ransac.max_iterations);
+
+            let again = ransac.run(&estimator).unwrap();
+            assert_eq!(again.inliers, result.inliers);
+            assert_eq!(again.iterations, result.iterations);
+        }
+    }
+
+    #[test]
+    fn prosac_uses_quality_order() {
+        let estimator = line_points(2);
+        // 内点排在前面
+        let mut order: Vec<usize> = (0..estimator.points.len()).filter(|i| i % 3 != 0).collect();
+        order.extend((0..estimator.points.len()).filter(|i| i % 3 == 0));
+        let ransac = Ransac { threshold: 0.1, ..Ransac::default() };
+        let result = ransac.run_prosac(&estimator, &order).unwrap();
+        check_line(&result);
+        assert!(result.iterations <= ransac.run(&estimator).unwrap().iterations);
+    }
+
+    #[test]
+    fn adaptive_iterations_follow_inlier_ratio() {
+        assert_eq!(adaptive_iterations(1.0, 5, 0.99), 1);
+        assert_eq!(adaptive_iterations(0.0, 5, 0.99), usize::MAX);
+        // log(0.01) / log(1 - 0.5^2) ≈ 16.01
+        assert_eq!(adaptive_iterations(0.5, 2, 0.99), 17);
+    }
+}
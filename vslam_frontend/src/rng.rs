@@ -0,0 +1,9 @@
+/// SplitMix64伪随机数，输出只由种子决定
+/// 用于需要可复现的随机采样，如BRIEF采样模式、LSH选位和RANSAC
+pub fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
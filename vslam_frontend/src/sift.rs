@@ -1,123 +1,231 @@
-use image::{DynamicImage, imageops::FilterType, GenericImageView, Pixel, ImageBuffer, Luma};
+use image::{GrayImage, ImageBuffer, Luma};
 use nalgebra::{Vector2, Vector3, Matrix3};
-use vslam_core::keyframe::Feature;
+use std::f64::consts::PI;
+use vslam_core::feature::{Descriptor, DescriptorExtractor, Detector, KeyPoint, Matcher};
 use imageproc::filter::gaussian_blur_f32;
 
-const OCTAVES: usize = 4; // 尺度空间层数
-const INTERVALS: usize = 3; // 单个尺度空间的影像层数
-const SIGMA: f64 = 1.6; // σ，标准差，高斯算子的参数
+/// 单通道浮点影像，灰度归一化到[0, 1]
+type FloatImage = ImageBuffer<Luma<f32>, Vec<f32>>;
 
-#[derive(Clone, Copy)]
+const INITIAL_SIGMA: f64 = 0.5; // 输入影像自带的模糊
+const IMAGE_BORDER: i32 = 5; // 检测极值时忽略的边界宽度
+const MAX_ITERATIONS: usize = 5; // 精确化迭代次数上限
+const ORIENTATION_BINS: usize = 36; // 方向直方图bin数
+const ORIENTATION_SIGMA_FACTOR: f64 = 1.5; // 方向直方图高斯权重σ = 1.5 * 特征点尺度
+const ORIENTATION_RADIUS_FACTOR: f64 = 3.0; // 方向直方图统计半径 = 3 * 高斯权重σ
+const ORIENTATION_PEAK_RATIO: f64 = 0.8; // 不低于最高峰80%的峰值都作为主方向
+const DESCRIPTOR_WIDTH: usize = 4; // 描述子区域划分为4x4个子区域
+const DESCRIPTOR_BINS: usize = 8; // 每个子区域8个方向
+const DESCRIPTOR_SCALE_FACTOR: f64 = 3.0; // 子区域边长 = 3 * 特征点尺度
+const DESCRIPTOR_MAGNITUDE_THRESHOLD: f32 = 0.2; // 归一化后单个分量的上限
+
+/// SIFT描述子长度
+pub const SIFT_DESCRIPTOR_SIZE: usize = DESCRIPTOR_WIDTH * DESCRIPTOR_WIDTH * DESCRIPTOR_BINS;
+
+/// SIFT特征：高斯差分极值点 + 128维梯度方向直方图描述子
+#[derive(Clone, Copy, Debug)]
 pub struct SIFT {
-    pub location: Vector2<f64>,
-    pub descriptor: [u64; 4],
+    pub octaves: usize,          // 尺度空间组数
+    pub intervals: usize,        // 每组检测极值的尺度数
+    pub sigma: f64,              // σ，标准差，高斯算子的参数
+    pub contrast_threshold: f64, // 关键点对比度阈值
+    pub edge_threshold: f64,     // 边缘响应阈值
+    pub ratio_threshold: f64,    // 匹配时的比值测试阈值
+    pub cross_check: bool,       // 匹配时是否要求互为最近邻
+}
+
+impl Default for SIFT {
+    fn default() -> Self {
+        SIFT {
+            octaves: 4,
+            intervals: 3,
+            sigma: 1.6,
+            contrast_threshold: 0.03,
+            edge_threshold: 10.0,
+            ratio_threshold: 0.8,
+            cross_check: false,
+        }
+    }
 }
 
-impl Feature for SIFT {
-    fn extract_features(image: &DynamicImage) -> Vec<Self>
-    where
-        Self: Sized,
-    {
-        let scale_space = build_scale_space(&image);
+impl SIFT {
+    /// 提取特征点和描述子，描述子直接在检测所用的高斯尺度空间上计算，不再重建金字塔
+    pub fn extract(&self, image: &GrayImage) -> (Vec<KeyPoint>, Vec<[f32; SIFT_DESCRIPTOR_SIZE]>) {
+        let scale_space = build_scale_space(image, self);
         let dog_space = build_difference_of_gaussians(&scale_space);
-        let keypoints = find_keypoints(&dog_space);
-        
-        // 在这里添加关键点精确化和方向分配步骤（Step 4）
-        
-        // 在这里添加描述子计算步骤（Step 5）
-        keypoints
+        let mut keypoints = find_keypoints(&scale_space, &dog_space, self);
+        let descriptors = compute_descriptors(&scale_space, &mut keypoints, self);
+        (keypoints, descriptors)
     }
 
-    fn match_features(feature1: &Vec<Self>, feature2: &Vec<Self>) -> Vec<(usize, usize)>
-    where
-        Self: Sized,
-    {
-        todo!()
+    /// 特征点尺度对应的组号、组内尺度和组内σ
+    fn locate_scale(&self, keypoint: &KeyPoint, octaves: usize) -> (usize, f64, f64) {
+        let sigma = (keypoint.size / 2.0).max(self.sigma);
+        let octave = ((sigma / self.sigma).log2().floor().max(0.0) as usize).min(octaves - 1);
+        let octave_sigma = sigma / 2f64.powi(octave as i32);
+        let interval = (self.intervals as f64 * (octave_sigma / self.sigma).log2())
+            .clamp(0.0, self.intervals as f64 + 2.0);
+        (octave, interval, octave_sigma)
     }
 }
 
+impl Detector for SIFT {
+    /// 检测到的特征点已分配主方向，多主方向的点会重复出现
+    fn detect(&self, image: &GrayImage) -> Vec<KeyPoint> {
+        let scale_space = build_scale_space(image, self);
+        let dog_space = build_difference_of_gaussians(&scale_space);
+        find_keypoints(&scale_space, &dog_space, self)
+    }
+}
+
+impl DescriptorExtractor for SIFT {
+    type Descriptor = [f32; SIFT_DESCRIPTOR_SIZE];
+
+    /// 使用特征点已有的size和angle，可用于其他检测器得到的特征点
+    fn compute(&self, image: &GrayImage, keypoints: &mut Vec<KeyPoint>) -> Vec<Self::Descriptor> {
+        let scale_space = build_scale_space(image, self);
+        compute_descriptors(&scale_space, keypoints, self)
+    }
+}
+
+impl Matcher<[f32; SIFT_DESCRIPTOR_SIZE]> for SIFT {
+    /// 欧氏距离最近邻匹配，比值测试剔除歧义匹配
+    /// cross_check为true时还要求train中的描述子反过来也以query中的描述子为最近邻
+    fn match_descriptors(&self, query: &[[f32; SIFT_DESCRIPTOR_SIZE]], train: &[[f32; SIFT_DESCRIPTOR_SIZE]]) -> Vec<(usize, usize)> {
+        let ratio_threshold = self.ratio_threshold;
+        let mut matches = Vec::new();
+
+        for (i, query_descriptor) in query.iter().enumerate() {
+            let (best_index, best_distance, second_best_distance) = nearest_two(query_descriptor, train);
+
+            // 比值测试
+            if best_distance < ratio_threshold * second_best_distance {
+                if let Some(best_j) = best_index {
+                    if self.cross_check && nearest_two(&train[best_j], query).0 != Some(i) {
+                        continue;
+                    }
+                    matches.push((i, best_j));
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+/// 在candidates中查找与descriptor距离最小的两个，返回(最近邻索引, 最小距离, 次小距离)
+fn nearest_two(descriptor: &[f32; SIFT_DESCRIPTOR_SIZE], candidates: &[[f32; SIFT_DESCRIPTOR_SIZE]]) -> (Option<usize>, f64, f64) {
+    let mut best_distance = f64::INFINITY;
+    let mut second_best_distance = f64::INFINITY;
+    let mut best_index = None;
+
+    // 保留最小的两个
+    for (j, candidate) in candidates.iter().enumerate() {
+        let distance = descriptor.distance(candidate);
+        if distance < best_distance {
+            second_best_distance = best_distance;
+            best_distance = distance;
+            best_index = Some(j);
+        } else if distance < second_best_distance {
+            second_best_distance = distance;
+        }
+    }
+
+    (best_index, best_distance, second_best_distance)
+}
+
+/// 读取像素值
+fn pixel(image: &FloatImage, x: i32, y: i32) -> f64 {
+    image.get_pixel(x as u32, y as u32).0[0] as f64
+}
+
 /// 构建高斯金字塔
-/// OCTAVES层
-/// 每层INTERVALS + 3张影像
-fn build_scale_space(image: &DynamicImage) -> Vec<Vec<DynamicImage>> {
-    let mut scale_space =
-        vec![vec![DynamicImage::new_luma8(image.width(), image.height()); INTERVALS + 3]; OCTAVES];
-
-    for octave in 0..OCTAVES {
-        for interval in 0..(INTERVALS + 3) {
-            let sigma = get_sigma(octave, interval);
-            let scaled_image = if octave == 0 && interval == 0 {
-                image.clone()
-            } else if interval == 0 {
-                let (width, height) = (
-                    scale_space[octave - 1][0].width() / 2,
-                    scale_space[octave - 1][0].height() / 2,
-                );
-                scale_space[octave - 1][0].resize(width, height, FilterType::Triangle)
-            } else {
-                smooth_image(&scale_space[octave][interval - 1], sigma)
-            };
-            scale_space[octave][interval] = scaled_image;
+/// octaves组，影像过小时提前结束
+/// 每组intervals + 3张影像，第i张的组内尺度为σ·2^(i/intervals)
+fn build_scale_space(image: &GrayImage, params: &SIFT) -> Vec<Vec<FloatImage>> {
+    let mut scale_space: Vec<Vec<FloatImage>> = Vec::with_capacity(params.octaves);
+    let base = FloatImage::from_fn(image.width(), image.height(), |x, y| {
+        Luma([image.get_pixel(x, y).0[0] as f32 / 255.0])
+    });
+    let initial_sigma = (params.sigma * params.sigma - INITIAL_SIGMA * INITIAL_SIGMA).max(0.01).sqrt();
+    let min_size = 2 * IMAGE_BORDER as u32 + 3;
+
+    for octave in 0..params.octaves {
+        let first = if octave == 0 {
+            smooth_image(&base, initial_sigma as f32)
+        } else {
+            // 上一组中尺度为2σ的影像降采样
+            let previous = &scale_space[octave - 1][params.intervals];
+            let (width, height) = (previous.width() / 2, previous.height() / 2);
+            FloatImage::from_fn(width, height, |x, y| *previous.get_pixel(2 * x, 2 * y))
+        };
+        if first.width() < min_size || first.height() < min_size {
+            break;
+        }
+
+        let mut images = Vec::with_capacity(params.intervals + 3);
+        images.push(first);
+        for interval in 1..(params.intervals + 3) {
+            let sigma = get_sigma(interval, params);
+            let smoothed = smooth_image(&images[interval - 1], sigma);
+            images.push(smoothed);
         }
+        scale_space.push(images);
     }
 
     scale_space
 }
 
-/// 根据OCTAVES和INTERVAL计算高斯滤波的σ
-fn get_sigma(octave: usize, interval: usize) -> f32 {
-    let k = 2f32.powf(1.0 / INTERVALS as f32);
-    SIGMA as f32 * k.powi(octave as i32 + interval as i32)
+/// 组内第interval-1张影像到第interval张影像需要的增量高斯σ
+fn get_sigma(interval: usize, params: &SIFT) -> f32 {
+    let k = 2f64.powf(1.0 / params.intervals as f64);
+    let previous = params.sigma * k.powi(interval as i32 - 1);
+    let total = previous * k;
+    (total * total - previous * previous).sqrt() as f32
 }
 
 /// 高斯滤波
-fn smooth_image(image: &DynamicImage, sigma: f32) -> DynamicImage {
-    let luma_image = image.to_luma8();
-    let smooth_image = gaussian_blur_f32(&luma_image, sigma);
-    DynamicImage::ImageLuma8(smooth_image)
+fn smooth_image(image: &FloatImage, sigma: f32) -> FloatImage {
+    gaussian_blur_f32(image, sigma)
 }
 
 /// 构建高斯差分金字塔
-/// OCTAVES层
-/// 每层INTERVALS + 2张影像
-fn build_difference_of_gaussians(scale_space: &Vec<Vec<DynamicImage>>) -> Vec<Vec<DynamicImage>> {
-    let mut dog_space = vec![vec![DynamicImage::new_luma8(scale_space[0][0].width(), scale_space[0][0].height()); INTERVALS+2]; OCTAVES];
-
-    for octave in 0..OCTAVES {
-        for interval in 0..INTERVALS+2 {
-            let width = scale_space[octave][interval].width();
-            let height = scale_space[octave][interval].height();
-            let mut difference_image = image::ImageBuffer::new(width, height);
-            for y in 0..height {
-                for x in 0..width {
-                    let value1 = scale_space[octave][interval].get_pixel(x, y)[0] as f32;
-                    let value2 = scale_space[octave][interval + 1].get_pixel(x, y)[0] as f32;
-                    difference_image.put_pixel(x, y, image::Luma([((value1 - value2) + 128.0) as u8]));
-                }
-            }
-            dog_space[octave][interval] = DynamicImage::ImageLuma8(difference_image);
-        }
-    }
-
-    dog_space
+/// 与高斯金字塔组数相同
+/// 每组比高斯金字塔少一张影像
+fn build_difference_of_gaussians(scale_space: &[Vec<FloatImage>]) -> Vec<Vec<FloatImage>> {
+    scale_space
+        .iter()
+        .map(|images| {
+            images
+                .windows(2)
+                .map(|pair| {
+                    FloatImage::from_fn(pair[0].width(), pair[0].height(), |x, y| {
+                        Luma([pair[1].get_pixel(x, y).0[0] - pair[0].get_pixel(x, y).0[0]])
+                    })
+                })
+                .collect()
+        })
+        .collect()
 }
 
 /// 检测特征点
-/// 每组检测INTERVAL个尺度特征点
-fn find_keypoints(dog_space: &Vec<Vec<DynamicImage>>) -> Vec<SIFT> {
+/// 每组检测intervals个尺度特征点，并分配主方向
+fn find_keypoints(scale_space: &[Vec<FloatImage>], dog_space: &[Vec<FloatImage>], params: &SIFT) -> Vec<KeyPoint> {
     let mut keypoints = Vec::new();
+    // 预筛选阈值
+    let threshold = 0.5 * params.contrast_threshold;
 
-    for octave in 0..OCTAVES {
-        for interval in 1..(INTERVALS +1) {
-            for y in 1..(dog_space[octave][interval].height() - 1) {
-                for x in 1..(dog_space[octave][interval].width() - 1) {
-                    if is_extrema(&dog_space[octave], interval, x, y) {
-                        
-                        if let Some(location) = refine_keypoint(&dog_space, octave, interval, x, y) {
-                            let descriptor = [0u64; 4];
-                            keypoints.push(SIFT { location, descriptor });
-                        }
-
+    for (octave, dogs) in dog_space.iter().enumerate() {
+        let width = dogs[0].width() as i32;
+        let height = dogs[0].height() as i32;
+        for interval in 1..(params.intervals + 1) {
+            for y in IMAGE_BORDER..(height - IMAGE_BORDER) {
+                for x in IMAGE_BORDER..(width - IMAGE_BORDER) {
+                    if pixel(&dogs[interval], x, y).abs() <= threshold || !is_extrema(dogs, interval, x, y) {
+                        continue;
+                    }
+                    if let Some(keypoint) = refine_keypoint(dog_space, octave, interval, x, y, params) {
+                        assign_orientations(scale_space, &keypoint, params, &mut keypoints);
                     }
                 }
             }
@@ -127,18 +235,22 @@ fn find_keypoints(dog_space: &Vec<Vec<DynamicImage>>) -> Vec<SIFT> {
     keypoints
 }
 
-/// 判断特征点，三维26个
-fn is_extrema(dog_space: &Vec<DynamicImage>, interval: usize, x: u32, y: u32) -> bool {
-    let center_pixel = dog_space[interval].get_pixel(x, y).to_luma()[0];
+/// 判断特征点，三维26个邻域的极大值或极小值
+fn is_extrema(dog_space: &[FloatImage], interval: usize, x: i32, y: i32) -> bool {
+    let center_pixel = pixel(&dog_space[interval], x, y);
+    let mut is_maximum = true;
+    let mut is_minimum = true;
 
-    for i in (interval - 1)..=(interval + 1) {
+    for (i, layer) in dog_space.iter().enumerate().take(interval + 2).skip(interval - 1) {
         for j in (y - 1)..=(y + 1) {
             for k in (x - 1)..=(x + 1) {
-                let neighbor_pixel = dog_space[i].get_pixel(k, j).to_luma()[0];
                 if i == interval && j == y && k == x {
                     continue;
                 }
-                if center_pixel <= neighbor_pixel {
+                let neighbor_pixel = pixel(layer, k, j);
+                is_maximum &= center_pixel > neighbor_pixel;
+                is_minimum &= center_pixel < neighbor_pixel;
+                if !is_maximum && !is_minimum {
                     return false;
                 }
             }
@@ -149,23 +261,25 @@ fn is_extrema(dog_space: &Vec<DynamicImage>, interval: usize, x: u32, y: u32) ->
 }
 
 /// 精确化精确点
-fn refine_keypoint(dog_space: &Vec<Vec<DynamicImage>>, octave: usize, interval: usize, x: u32, y: u32) -> Option<Vector2<f64>> {
-    const MAX_ITERATIONS: u8 = 5; // 定义迭代次数上限
-    const CONTRAST_THRESHOLD: f64 = 0.03;// 定义关键点对比度阈值
-    const EDGE_THRESHOLD: f64 = 10.0;// 定义边缘响应阈值
-    
-    let mut x = x as f64;
-    let mut y = y as f64;
-    let mut interval = interval as f64;
-    
+/// 返回的特征点位置已换算到原图尺度
+fn refine_keypoint(dog_space: &[Vec<FloatImage>], octave: usize, interval: usize, x: i32, y: i32, params: &SIFT) -> Option<KeyPoint> {
+    let contrast_threshold = params.contrast_threshold;// 关键点对比度阈值
+    let edge_threshold = params.edge_threshold;// 边缘响应阈值
+    let width = dog_space[octave][0].width() as i32;
+    let height = dog_space[octave][0].height() as i32;
+
+    let mut x = x;
+    let mut y = y;
+    let mut interval = interval;
+
     for _ in 0..MAX_ITERATIONS {
-        let gradients = compute_gradients(&dog_space, octave, interval as usize, x, y);
-        let hessian = compute_hessian(&dog_space, octave, interval as usize, x, y);
-        let offset = -hessian.try_inverse().unwrap() * gradients;
-        
-        if offset.norm() <= 0.5 {
-            let contrast = dog_space[octave][interval as usize].get_pixel(x as u32, y as u32).to_luma()[0] as f64 / 255.0 + 0.5 * gradients.dot(&offset);
-            if contrast.abs() < CONTRAST_THRESHOLD {
+        let gradients = compute_gradients(&dog_space[octave], interval, x, y);
+        let hessian = compute_hessian(&dog_space[octave], interval, x, y);
+        let offset = -hessian.try_inverse()? * gradients;
+
+        if offset.iter().all(|value| value.abs() < 0.5) {
+            let contrast = pixel(&dog_space[octave][interval], x, y) + 0.5 * gradients.dot(&offset);
+            if contrast.abs() < contrast_threshold {
                 return None;
             }
 
@@ -173,35 +287,40 @@ fn refine_keypoint(dog_space: &Vec<Vec<DynamicImage>>, octave: usize, interval:
             let determinant = hessian[(0, 0)] * hessian[(1, 1)] - hessian[(0, 1)] * hessian[(1, 0)];
             let curvature_ratio = trace * trace / determinant;
 
-            if determinant <= 0.0 || curvature_ratio > (EDGE_THRESHOLD + 1.0) * (EDGE_THRESHOLD + 1.0) / EDGE_THRESHOLD {
+            if determinant <= 0.0 || curvature_ratio > (edge_threshold + 1.0) * (edge_threshold + 1.0) / edge_threshold {
                 return None;
             }
 
-            break;
+            let scale = 2f64.powi(octave as i32);
+            let subinterval = interval as f64 + offset[2];
+            return Some(KeyPoint {
+                location: Vector2::new(x as f64 + offset[0], y as f64 + offset[1]) * scale,
+                size: 2.0 * params.sigma * 2f64.powf(subinterval / params.intervals as f64) * scale,
+                angle: 0.0,
+                response: contrast.abs(),
+                octave,
+            });
         }
-        
-        x += offset[0];
-        y += offset[1];
-        interval += offset[2];
-        
-        if x < 1.0 || x > dog_space[octave][0].width() as f64 - 2.0 || y < 1.0 || y > dog_space[octave][0].height() as f64 - 2.0 || interval < 1.0 || interval > INTERVALS as f64 {
+
+        x += offset[0].round() as i32;
+        y += offset[1].round() as i32;
+        let next_interval = interval as i32 + offset[2].round() as i32;
+
+        if x < IMAGE_BORDER || x >= width - IMAGE_BORDER || y < IMAGE_BORDER || y >= height - IMAGE_BORDER || next_interval < 1 || next_interval > params.intervals as i32 {
             return None;
         }
+        interval = next_interval as usize;
     }
-    
 
-    
-    Some(Vector2::new(x, y))
+    // 未收敛
+    None
 }
 
-/// 计算图像的梯度幅值和方向
-fn compute_gradients(dog_space: &Vec<Vec<DynamicImage>>, octave: usize, interval: usize, x: f64, y: f64) -> Vector3<f64> {
-    let dx = (dog_space[octave][interval].get_pixel((x + 1.0) as u32, y as u32).to_luma()[0] as f64
-        - dog_space[octave][interval].get_pixel((x - 1.0) as u32, y as u32).to_luma()[0] as f64) / 2.0;
-    let dy = (dog_space[octave][interval].get_pixel(x as u32, (y + 1.0) as u32).to_luma()[0] as f64
-        - dog_space[octave][interval].get_pixel(x as u32, (y - 1.0) as u32).to_luma()[0] as f64) / 2.0;
-    let ds = (dog_space[octave][interval + 1].get_pixel(x as u32, y as u32).to_luma()[0] as f64
-        - dog_space[octave][interval - 1].get_pixel(x as u32, y as u32).to_luma()[0] as f64) / 2.0;
+/// 计算高斯差分在(x, y, σ)三个方向的一阶导数
+fn compute_gradients(dog_space: &[FloatImage], interval: usize, x: i32, y: i32) -> Vector3<f64> {
+    let dx = (pixel(&dog_space[interval], x + 1, y) - pixel(&dog_space[interval], x - 1, y)) / 2.0;
+    let dy = (pixel(&dog_space[interval], x, y + 1) - pixel(&dog_space[interval], x, y - 1)) / 2.0;
+    let ds = (pixel(&dog_space[interval + 1], x, y) - pixel(&dog_space[interval - 1], x, y)) / 2.0;
 
     Vector3::new(dx, dy, ds)
 }
@@ -209,34 +328,271 @@ fn compute_gradients(dog_space: &Vec<Vec<DynamicImage>>, octave: usize, interval
 /// 计算海森矩阵
 /// 一个3x3矩阵
 /// 关键点位置处的二阶导数
-fn compute_hessian(dog_space: &Vec<Vec<DynamicImage>>, octave: usize, interval: usize, x: f64, y: f64) -> Matrix3<f64> {
-    let dxx = dog_space[octave][interval].get_pixel((x + 1.0) as u32, y as u32).to_luma()[0] as f64
-        - 2.0 * dog_space[octave][interval].get_pixel(x as u32, y as u32).to_luma()[0] as f64
-        + dog_space[octave][interval].get_pixel((x - 1.0) as u32, y as u32).to_luma()[0] as f64;
-
-    let dyy = dog_space[octave][interval].get_pixel(x as u32, (y + 1.0) as u32).to_luma()[0] as f64
-        - 2.0 * dog_space[octave][interval].get_pixel(x as u32, y as u32).to_luma()[0] as f64
-        + dog_space[octave][interval].get_pixel(x as u32, (y - 1.0) as u32).to_luma()[0] as f64;
-
-    let dss = dog_space[octave][interval + 1].get_pixel(x as u32, y as u32).to_luma()[0] as f64
-        - 2.0 * dog_space[octave][interval].get_pixel(x as u32, y as u32).to_luma()[0] as f64
-        + dog_space[octave][interval - 1].get_pixel(x as u32, y as u32).to_luma()[0] as f64;
-
-    let dxy = ((dog_space[octave][interval].get_pixel((x + 1.0) as u32, (y + 1.0) as u32).to_luma()[0] as f64
-        - dog_space[octave][interval].get_pixel((x - 1.0) as u32, (y + 1.0) as u32).to_luma()[0] as f64)
-        - (dog_space[octave][interval].get_pixel((x + 1.0) as u32, (y - 1.0) as u32).to_luma()[0] as f64
-        - dog_space[octave][interval].get_pixel((x - 1.0) as u32, (y - 1.0) as u32).to_luma()[0] as f64)) / 4.0;
-
-    let dxs = ((dog_space[octave][interval + 1].get_pixel((x + 1.0) as u32, y as u32).to_luma()[0] as f64
-        - dog_space[octave][interval - 1].get_pixel((x + 1.0) as u32, y as u32).to_luma()[0] as f64)
-        - (dog_space[octave][interval + 1].get_pixel((x - 1.0) as u32, y as u32).to_luma()[0] as f64
-        - dog_space[octave][interval - 1].get_pixel((x - 1.0) as u32, y as u32).to_luma()[0] as f64)) / 4.0;
-    let dys = ((dog_space[octave][interval + 1].get_pixel(x as u32, (y + 1.0) as u32).to_luma()[0] as f64
-        - dog_space[octave][interval - 1].get_pixel(x as u32, (y + 1.0) as u32).to_luma()[0] as f64)
-        - (dog_space[octave][interval + 1].get_pixel(x as u32, (y - 1.0) as u32).to_luma()[0] as f64
-        - dog_space[octave][interval - 1].get_pixel(x as u32, (y - 1.0) as u32).to_luma()[0] as f64)) / 4.0;
-    
+fn compute_hessian(dog_space: &[FloatImage], interval: usize, x: i32, y: i32) -> Matrix3<f64> {
+    let current = &dog_space[interval];
+    let above = &dog_space[interval + 1];
+    let below = &dog_space[interval - 1];
+    let center = pixel(current, x, y);
+
+    let dxx = pixel(current, x + 1, y) - 2.0 * center + pixel(current, x - 1, y);
+    let dyy = pixel(current, x, y + 1) - 2.0 * center + pixel(current, x, y - 1);
+    let dss = pixel(above, x, y) - 2.0 * center + pixel(below, x, y);
+
+    let dxy = ((pixel(current, x + 1, y + 1) - pixel(current, x - 1, y + 1))
+        - (pixel(current, x + 1, y - 1) - pixel(current, x - 1, y - 1))) / 4.0;
+    let dxs = ((pixel(above, x + 1, y) - pixel(below, x + 1, y))
+        - (pixel(above, x - 1, y) - pixel(below, x - 1, y))) / 4.0;
+    let dys = ((pixel(above, x, y + 1) - pixel(below, x, y + 1))
+        - (pixel(above, x, y - 1) - pixel(below, x, y - 1))) / 4.0;
+
     Matrix3::new(dxx, dxy, dxs,
                  dxy, dyy, dys,
                  dxs, dys, dss)
-    }
\ No newline at end of file
+}
+
+/// 高斯影像在(x, y)处的梯度(dx, dy)，越界返回None
+fn image_gradient(image: &FloatImage, x: i32, y: i32) -> Option<(f64, f64)> {
+    if x < 1 || y < 1 || x >= image.width() as i32 - 1 || y >= image.height() as i32 - 1 {
+        return None;
+    }
+    let dx = pixel(image, x + 1, y) - pixel(image, x - 1, y);
+    let dy = pixel(image, x, y + 1) - pixel(image, x, y - 1);
+    Some((dx, dy))
+}
+
+/// 方向分配
+/// 统计特征点邻域梯度方向直方图，不低于最高峰80%的每个峰值都生成一个特征点
+fn assign_orientations(scale_space: &[Vec<FloatImage>], keypoint: &KeyPoint, params: &SIFT, keypoints: &mut Vec<KeyPoint>) {
+    let (octave, interval, octave_sigma) = params.locate_scale(keypoint, scale_space.len());
+    let image = &scale_space[octave][interval.round() as usize];
+    let scale = 2f64.powi(octave as i32);
+    let center_x = (keypoint.location.x / scale).round() as i32;
+    let center_y = (keypoint.location.y / scale).round() as i32;
+
+    let weight_sigma = ORIENTATION_SIGMA_FACTOR * octave_sigma;
+    let radius = (ORIENTATION_RADIUS_FACTOR * weight_sigma).round() as i32;
+    let mut histogram = [0.0; ORIENTATION_BINS];
+
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if let Some((gx, gy)) = image_gradient(image, center_x + dx, center_y + dy) {
+                let weight = (-((dx * dx + dy * dy) as f64) / (2.0 * weight_sigma * weight_sigma)).exp();
+                let magnitude = (gx * gx + gy * gy).sqrt();
+                let angle = gy.atan2(gx).rem_euclid(2.0 * PI);
+                let bin = ((angle / (2.0 * PI) * ORIENTATION_BINS as f64).round() as usize) % ORIENTATION_BINS;
+                histogram[bin] += weight * magnitude;
+            }
+        }
+    }
+
+    // 平滑直方图
+    let n = ORIENTATION_BINS;
+    let smoothed: Vec<f64> = (0..n)
+        .map(|i| {
+            (histogram[(i + n - 2) % n] + histogram[(i + 2) % n]) / 16.0
+                + (histogram[(i + n - 1) % n] + histogram[(i + 1) % n]) * 4.0 / 16.0
+                + histogram[i] * 6.0 / 16.0
+        })
+        .collect();
+
+    let max_value = smoothed.iter().cloned().fold(0.0, f64::max);
+    if max_value <= 0.0 {
+        return;
+    }
+    for i in 0..n {
+        let left = smoothed[(i + n - 1) % n];
+        let right = smoothed[(i + 1) % n];
+        let value = smoothed[i];
+        if value > left && value > right && value >= ORIENTATION_PEAK_RATIO * max_value {
+            // 抛物线插值峰值位置
+            let offset = 0.5 * (left - right) / (left - 2.0 * value + right);
+            let bin = (i as f64 + offset).rem_euclid(n as f64);
+            keypoints.push(KeyPoint {
+                angle: bin / n as f64 * 2.0 * PI,
+                ..*keypoint
+            });
+        }
+    }
+}
+
+/// 计算描述子
+/// 靠近边界无法计算描述子的特征点会被移除
+fn compute_descriptors(scale_space: &[Vec<FloatImage>], keypoints: &mut Vec<KeyPoint>, params: &SIFT) -> Vec<[f32; SIFT_DESCRIPTOR_SIZE]> {
+    let mut kept = Vec::with_capacity(keypoints.len());
+    let mut descriptors = Vec::with_capacity(keypoints.len());
+    if scale_space.is_empty() {
+        keypoints.clear();
+        return descriptors;
+    }
+    for keypoint in keypoints.iter() {
+        if let Some(descriptor) = compute_descriptor(scale_space, keypoint, params) {
+            kept.push(*keypoint);
+            descriptors.push(descriptor);
+        }
+    }
+    *keypoints = kept;
+    descriptors
+}
+
+/// 单个特征点的128维描述子
+/// 将旋转到主方向后的邻域划分为4x4个子区域，每个子区域统计8个方向的梯度直方图
+fn compute_descriptor(scale_space: &[Vec<FloatImage>], keypoint: &KeyPoint, params: &SIFT) -> Option<[f32; SIFT_DESCRIPTOR_SIZE]> {
+    let (octave, interval, octave_sigma) = params.locate_scale(keypoint, scale_space.len());
+    let image = &scale_space[octave][interval.round() as usize];
+    let scale = 2f64.powi(octave as i32);
+    let center_x = (keypoint.location.x / scale).round() as i32;
+    let center_y = (keypoint.location.y / scale).round() as i32;
+    if center_x < 0 || center_y < 0 || center_x >= image.width() as i32 || center_y >= image.height() as i32 {
+        return None;
+    }
+
+    let d = DESCRIPTOR_WIDTH as f64;
+    let cell_width = DESCRIPTOR_SCALE_FACTOR * octave_sigma;
+    let radius = (cell_width * std::f64::consts::SQRT_2 * (d + 1.0) * 0.5).round() as i32;
+    let (sin_angle, cos_angle) = keypoint.angle.sin_cos();
+    // 高斯权重σ为描述子窗口宽度的一半
+    let weight_scale = -1.0 / (2.0 * (0.5 * d) * (0.5 * d));
+    let bins_per_radian = DESCRIPTOR_BINS as f64 / (2.0 * PI);
+
+    // 多留一圈用于三线性插值
+    let mut histogram = [[[0.0f64; DESCRIPTOR_BINS]; DESCRIPTOR_WIDTH + 2]; DESCRIPTOR_WIDTH + 2];
+
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            // 旋转到特征点坐标系，单位为子区域边长
+            let col = (dx as f64 * cos_angle + dy as f64 * sin_angle) / cell_width;
+            let row = (-dx as f64 * sin_angle + dy as f64 * cos_angle) / cell_width;
+            let row_bin = row + 0.5 * d - 0.5;
+            let col_bin = col + 0.5 * d - 0.5;
+            if row_bin <= -1.0 || row_bin >= d || col_bin <= -1.0 || col_bin >= d {
+                continue;
+            }
+            let (gx, gy) = match image_gradient(image, center_x + dx, center_y + dy) {
+                Some(gradient) => gradient,
+                None => continue,
+            };
+
+            let weight = ((col * col + row * row) * weight_scale).exp();
+            let magnitude = (gx * gx + gy * gy).sqrt() * weight;
+            let orientation = (gy.atan2(gx) - keypoint.angle).rem_euclid(2.0 * PI) * bins_per_radian;
+
+            // 三线性插值分配到相邻的行、列、方向bin
+            let r0 = row_bin.floor();
+            let c0 = col_bin.floor();
+            let o0 = orientation.floor();
+            let (dr, dc, dori) = (row_bin - r0, col_bin - c0, orientation - o0);
+            for (ri, row_weight) in [(0, 1.0 - dr), (1, dr)] {
+                for (ci, col_weight) in [(0, 1.0 - dc), (1, dc)] {
+                    for (oi, orientation_weight) in [(0, 1.0 - dori), (1, dori)] {
+                        let r = (r0 as i32 + 1 + ri) as usize;
+                        let c = (c0 as i32 + 1 + ci) as usize;
+                        let o = (o0 as usize + oi) % DESCRIPTOR_BINS;
+                        histogram[r][c][o] += magnitude * row_weight * col_weight * orientation_weight;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut descriptor = [0.0f32; SIFT_DESCRIPTOR_SIZE];
+    for r in 0..DESCRIPTOR_WIDTH {
+        for c in 0..DESCRIPTOR_WIDTH {
+            for o in 0..DESCRIPTOR_BINS {
+                descriptor[(r * DESCRIPTOR_WIDTH + c) * DESCRIPTOR_BINS + o] = histogram[r + 1][c + 1][o] as f32;
+            }
+        }
+    }
+
+    // 归一化，截断过大的分量以降低光照变化的影响，再次归一化
+    let norm = descriptor.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm <= f32::EPSILON {
+        return None;
+    }
+    for value in descriptor.iter_mut() {
+        *value = (*value / norm).min(DESCRIPTOR_MAGNITUDE_THRESHOLD);
+    }
+    let norm = descriptor.iter().map(|v| v * v).sum::<f32>().sqrt();
+    for value in descriptor.iter_mut() {
+        *value /= norm;
+    }
+
+    Some(descriptor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::imageops::rotate90;
+
+    /// 若干大小不同的高斯斑点
+    fn blob_image() -> GrayImage {
+        blob_image_scaled(1.0)
+    }
+
+    /// 按scale放大绘制的blob_image
+    fn blob_image_scaled(scale: f64) -> GrayImage {
+        let blobs = [(40.0, 50.0, 4.0), (90.0, 40.0, 6.0), (70.0, 100.0, 3.0), (120.0, 110.0, 5.0), (30.0, 120.0, 7.0)];
+        GrayImage::from_fn((160.0 * scale) as u32, (150.0 * scale) as u32, |x, y| {
+            let (x, y) = (x as f64 / scale, y as f64 / scale);
+            let value: f64 = blobs
+                .iter()
+                .map(|&(bx, by, s)| {
+                    let d2 = (x - bx).powi(2) + (y - 0.7 * by - 0.3 * bx).powi(2);
+                    200.0 * (-d2 / (2.0 * s * s)).exp()
+                })
+                .sum();
+            image::Luma([(30.0 + value).min(255.0) as u8])
+        })
+    }
+
+    #[test]
+    fn descriptors_are_rotation_invariant() {
+        let sift = SIFT::default();
+        let image = blob_image();
+        let (keypoints, descriptors) = sift.extract(&image);
+        assert!(!keypoints.is_empty());
+        assert_eq!(keypoints.len(), descriptors.len());
+
+        // 顺时针旋转90°：(x, y) → (h - 1 - y, x)，方向角增加π/2
+        let rotated = rotate90(&image);
+        let (rotated_keypoints, rotated_descriptors) = sift.extract(&rotated);
+        let height = image.height() as f64;
+
+        let mut num_matched = 0;
+        for (keypoint, descriptor) in keypoints.iter().zip(descriptors.iter()) {
+            let expected = Vector2::new(height - 1.0 - keypoint.location.y, keypoint.location.x);
+            let expected_angle = (keypoint.angle + PI / 2.0).rem_euclid(2.0 * PI);
+            let best = rotated_keypoints
+                .iter()
+                .zip(rotated_descriptors.iter())
+                .filter(|(other, _)| (other.location - expected).norm() < 1.5)
+                .filter(|(other, _)| {
+                    let difference = (other.angle - expected_angle).rem_euclid(2.0 * PI);
+                    difference.min(2.0 * PI - difference) < 0.2
+                })
+                .map(|(_, other)| descriptor.distance(other))
+                .fold(f64::INFINITY, f64::min);
+            if best < 0.3 {
+                num_matched += 1;
+            }
+        }
+        assert!(num_matched * 10 >= keypoints.len() * 8, "{} / {}", num_matched, keypoints.len());
+    }
+
+    #[test]
+    fn matches_scaled_image() {
+        // 放大2倍后，同一斑点的位置和尺度都应加倍
+        let sift = SIFT::default();
+        let (keypoints, descriptors) = sift.extract(&blob_image());
+        let (scaled_keypoints, scaled_descriptors) = sift.extract(&blob_image_scaled(2.0));
+        let corresponds = |keypoint: &KeyPoint, scaled: &KeyPoint| {
+            (scaled.location - 2.0 * keypoint.location).norm() < 3.0 && (1.6..2.5).contains(&(scaled.size / keypoint.size))
+        };
+        assert!(!keypoints.is_empty());
+        assert!(keypoints.iter().all(|keypoint| scaled_keypoints.iter().any(|scaled| corresponds(keypoint, scaled))));
+
+        let matches = sift.match_descriptors(&descriptors, &scaled_descriptors);
+        assert!(!matches.is_empty());
+        assert!(matches.iter().all(|&(i, j)| corresponds(&keypoints[i], &scaled_keypoints[j])));
+    }
+}
@@ -1,25 +1,398 @@
-use image::{DynamicImage,GrayImage};
-use nalgebra::Vector2;
-use vslam_core::keyframe::Feature;
+use image::GrayImage;
+use nalgebra::{Matrix3, Vector2, Vector3};
+use std::f64::consts::PI;
+use vslam_core::feature::{Descriptor, DescriptorExtractor, Detector, KeyPoint, Matcher};
 
+const INITIAL_FILTER_SIZE: u32 = 9; // 第一组第一层盒子滤波器边长，对应σ = 1.2
+const FILTER_SIZE_INCREMENT: u32 = 6; // 第一组相邻层滤波器边长之差，每组翻倍
+const DXY_WEIGHT: f64 = 0.9; // 盒子滤波近似高斯二阶导数时Dxy的补偿系数
+const ORIENTATION_RADIUS: i32 = 6; // 方向统计半径，单位为特征点尺度s
+const ORIENTATION_SIGMA: f64 = 2.0; // 方向统计高斯权重σ，单位为s
+const ORIENTATION_WINDOW: f64 = PI / 3.0; // 方向滑动窗口宽度
+const ORIENTATION_STEPS: usize = 72; // 滑动窗口起始角度数
+const DESCRIPTOR_REGIONS: usize = 4; // 描述子区域划分为4x4个子区域
+const DESCRIPTOR_SAMPLES: usize = 5; // 每个子区域5x5个采样点
+const DESCRIPTOR_SIGMA: f64 = 3.3; // 描述子高斯权重σ，单位为s
 
+/// SURF特征：Hessian盒子滤波极值点 + Haar小波描述子
+#[derive(Clone, Copy, Debug)]
 pub struct SURF{
-    pub location:Vector2<f64>,
-    pub descriptor:[u64;2],
+    pub hessian_threshold:f64,// Hessian响应阈值
+    pub octaves:usize,// 尺度空间组数
+    pub octave_layers:usize,// 每组检测极值的层数
+    pub extended:bool,// 是否使用128维描述子，否则为64维
+    pub ratio_threshold:f64,// 匹配时的比值测试阈值
 }
-impl Feature for SURF {
-    fn extract_features(image:&DynamicImage)->Vec<Self> where Self:Sized {
-        let gray_image = image::imageops::grayscale(image);
+
+impl Default for SURF {
+    fn default() -> Self {
+        SURF { hessian_threshold: 100.0, octaves: 4, octave_layers: 2, extended: false, ratio_threshold: 0.8 }
+    }
+}
+
+/// SURF描述子
+#[derive(Clone, Debug, PartialEq)]
+pub struct SurfDescriptor {
+    pub laplacian: bool,  // Hessian矩阵迹的符号，true为正（暗斑点）
+    pub values: Vec<f32>, // 64或128维，单位长度
+}
+
+/// 欧氏距离，不考虑laplacian
+impl Descriptor for SurfDescriptor {
+    fn distance(&self, other: &Self) -> f64 {
+        self.values
+            .iter()
+            .zip(other.values.iter())
+            .map(|(a, b)| ((a - b) * (a - b)) as f64)
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// 按f32切片访问描述子，用于KdForest
+impl AsRef<[f32]> for SurfDescriptor {
+    fn as_ref(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+impl SURF {
+    /// 提取特征点和描述子，Hessian检测与Haar小波描述子共用同一幅积分图
+    pub fn extract(&self, image:&GrayImage)->(Vec<KeyPoint>, Vec<SurfDescriptor>) {
+        let integral_image = compute_integral_image(image);
+        let mut keypoints = find_keypoints(&integral_image, self);
+        let descriptors = compute_descriptors(&integral_image, &mut keypoints, self.extended);
+        (keypoints, descriptors)
+    }
+}
+
+impl Detector for SURF {
+    /// 检测到的特征点已分配主方向，size为插值后的盒子滤波器边长
+    fn detect(&self, image:&GrayImage)->Vec<KeyPoint> {
         // 计算积分图
-        let integral_image = compute_integral_image(&gray_image);
-        
-        
-        todo!()
+        let integral_image = compute_integral_image(image);
+        find_keypoints(&integral_image, self)
+    }
+}
+
+impl DescriptorExtractor for SURF {
+    type Descriptor = SurfDescriptor;
+
+    /// 使用特征点已有的size和angle
+    fn compute(&self, image:&GrayImage, keypoints:&mut Vec<KeyPoint>)->Vec<SurfDescriptor> {
+        let integral_image = compute_integral_image(image);
+        compute_descriptors(&integral_image, keypoints, self.extended)
+    }
+}
+
+impl Matcher<SurfDescriptor> for SURF {
+    /// 欧氏距离最近邻匹配，laplacian符号不同的描述子直接跳过
+    fn match_descriptors(&self, query:&[SurfDescriptor], train:&[SurfDescriptor])->Vec<(usize,usize)> {
+        let ratio_threshold = self.ratio_threshold;
+        let mut matches = Vec::new();
+
+        for (i, query_descriptor) in query.iter().enumerate() {
+            let mut best_distance = f64::INFINITY;
+            let mut second_best_distance = f64::INFINITY;
+            let mut best_index = None;
+
+            // 保留最小的两个
+            for (j, train_descriptor) in train.iter().enumerate() {
+                // 亮斑点与暗斑点不可能匹配
+                if query_descriptor.laplacian != train_descriptor.laplacian {
+                    continue;
+                }
+                let distance = query_descriptor.distance(train_descriptor);
+                if distance < best_distance {
+                    second_best_distance = best_distance;
+                    best_distance = distance;
+                    best_index = Some(j);
+                } else if distance < second_best_distance {
+                    second_best_distance = distance;
+                }
+            }
+
+            // 比值测试
+            if best_distance < ratio_threshold * second_best_distance {
+                if let Some(best_j) = best_index {
+                    matches.push((i, best_j));
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+/// 一层Hessian响应，在间隔step的网格上采样
+struct ResponseLayer {
+    size: u32,          // 盒子滤波器边长
+    step: u32,          // 采样间隔
+    width: u32,         // 网格宽
+    height: u32,        // 网格高
+    border: u32,        // 滤波器超出影像的网格边界宽度
+    responses: Vec<f64>, // 行优先
+}
+
+impl ResponseLayer {
+    fn new(integral_image: &[Vec<f64>], size: u32, step: u32) -> Self {
+        let width = integral_image.len() as u32 / step;
+        let height = integral_image.first().map_or(0, |column| column.len()) as u32 / step;
+        let border = (size / 2 + 1).div_ceil(step);
+        let mut responses = vec![0.0; (width * height) as usize];
+        if width > 2 * border && height > 2 * border {
+            for y in border..(height - border) {
+                for x in border..(width - border) {
+                    responses[(y * width + x) as usize] = box_filter_response(integral_image, x * step, y * step, size);
+                }
+            }
+        }
+        ResponseLayer { size, step, width, height, border, responses }
+    }
+
+    fn response(&self, x: i32, y: i32) -> f64 {
+        self.responses[(y * self.width as i32 + x) as usize]
+    }
+}
+
+/// 检测特征点
+/// 每组octave_layers + 2层响应，中间层在3x3x3邻域内做非极大值抑制，再插值精确化并分配主方向
+fn find_keypoints(integral_image: &[Vec<f64>], params: &SURF) -> Vec<KeyPoint> {
+    let mut keypoints = Vec::new();
+
+    for octave in 0..params.octaves {
+        let step = 1 << octave;
+        let layers: Vec<ResponseLayer> = (0..params.octave_layers + 2)
+            .map(|layer| {
+                let size = (INITIAL_FILTER_SIZE + FILTER_SIZE_INCREMENT * layer as u32) << octave;
+                ResponseLayer::new(integral_image, size, step)
+            })
+            .collect();
+
+        for layer in 1..=params.octave_layers {
+            // 最大的滤波器决定有效区域
+            let border = layers[layer + 1].border as i32;
+            let width = layers[layer].width as i32;
+            let height = layers[layer].height as i32;
+            for y in border..(height - border) {
+                for x in border..(width - border) {
+                    let value = layers[layer].response(x, y);
+                    if value <= params.hessian_threshold || !is_maximum(&layers, layer, x, y) {
+                        continue;
+                    }
+                    if let Some(mut keypoint) = interpolate_extremum(&layers, layer, x, y, octave) {
+                        keypoint.angle = compute_orientation(integral_image, &keypoint);
+                        keypoints.push(keypoint);
+                    }
+                }
+            }
+        }
+    }
+
+    keypoints
+}
+
+/// 判断是否为3x3x3邻域内的极大值
+fn is_maximum(layers: &[ResponseLayer], layer: usize, x: i32, y: i32) -> bool {
+    let value = layers[layer].response(x, y);
+    for (i, neighbor_layer) in layers.iter().enumerate().take(layer + 2).skip(layer - 1) {
+        for j in (y - 1)..=(y + 1) {
+            for k in (x - 1)..=(x + 1) {
+                if i == layer && j == y && k == x {
+                    continue;
+                }
+                if neighbor_layer.response(k, j) >= value {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// 二次函数拟合极值点的亚像素位置和尺度
+/// 偏移超过一个采样间隔时认为不稳定
+fn interpolate_extremum(layers: &[ResponseLayer], layer: usize, x: i32, y: i32, octave: usize) -> Option<KeyPoint> {
+    let below = &layers[layer - 1];
+    let current = &layers[layer];
+    let above = &layers[layer + 1];
+    let center = current.response(x, y);
+
+    let gradients = Vector3::new(
+        (current.response(x + 1, y) - current.response(x - 1, y)) / 2.0,
+        (current.response(x, y + 1) - current.response(x, y - 1)) / 2.0,
+        (above.response(x, y) - below.response(x, y)) / 2.0,
+    );
+
+    let dxx = current.response(x + 1, y) - 2.0 * center + current.response(x - 1, y);
+    let dyy = current.response(x, y + 1) - 2.0 * center + current.response(x, y - 1);
+    let dss = above.response(x, y) - 2.0 * center + below.response(x, y);
+    let dxy = (current.response(x + 1, y + 1) - current.response(x - 1, y + 1)
+        - current.response(x + 1, y - 1) + current.response(x - 1, y - 1)) / 4.0;
+    let dxs = (above.response(x + 1, y) - above.response(x - 1, y)
+        - below.response(x + 1, y) + below.response(x - 1, y)) / 4.0;
+    let dys = (above.response(x, y + 1) - above.response(x, y - 1)
+        - below.response(x, y + 1) + below.response(x, y - 1)) / 4.0;
+    let hessian = Matrix3::new(dxx, dxy, dxs,
+                               dxy, dyy, dys,
+                               dxs, dys, dss);
+
+    let offset = -hessian.try_inverse()? * gradients;
+    if offset.iter().any(|value| value.abs() > 1.0) {
+        return None;
+    }
+
+    let step = current.step as f64;
+    let size_step = (above.size - current.size) as f64;
+    Some(KeyPoint {
+        location: Vector2::new((x as f64 + offset[0]) * step, (y as f64 + offset[1]) * step),
+        size: current.size as f64 + offset[2] * size_step,
+        angle: 0.0,
+        response: center + 0.5 * gradients.dot(&offset),
+        octave,
+    })
+}
+
+/// 盒子滤波器边长对应的尺度s
+fn keypoint_scale(keypoint: &KeyPoint) -> f64 {
+    1.2 * keypoint.size / INITIAL_FILTER_SIZE as f64
+}
+
+/// 方向分配
+/// 半径6s内以s为间隔计算边长4s的Haar小波响应，高斯加权后用π/3的滑动窗口求和，取模最大的窗口方向
+fn compute_orientation(integral_image: &[Vec<f64>], keypoint: &KeyPoint) -> f64 {
+    let scale = keypoint_scale(keypoint);
+    let haar_size = 2 * (2.0 * scale).round().max(1.0) as i32;
+    let center_x = keypoint.location.x;
+    let center_y = keypoint.location.y;
+
+    let mut samples = Vec::new();
+    for j in -ORIENTATION_RADIUS..=ORIENTATION_RADIUS {
+        for i in -ORIENTATION_RADIUS..=ORIENTATION_RADIUS {
+            if i * i + j * j >= ORIENTATION_RADIUS * ORIENTATION_RADIUS {
+                continue;
+            }
+            let x = (center_x + i as f64 * scale).round() as i32;
+            let y = (center_y + j as f64 * scale).round() as i32;
+            let weight = (-((i * i + j * j) as f64) / (2.0 * ORIENTATION_SIGMA * ORIENTATION_SIGMA)).exp();
+            let dx = weight * haar_x(integral_image, x, y, haar_size);
+            let dy = weight * haar_y(integral_image, x, y, haar_size);
+            samples.push((dy.atan2(dx), dx, dy));
+        }
+    }
+
+    let mut best_norm = 0.0;
+    let mut best_angle = 0.0;
+    for k in 0..ORIENTATION_STEPS {
+        let start = -PI + 2.0 * PI * k as f64 / ORIENTATION_STEPS as f64;
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        for &(angle, dx, dy) in &samples {
+            if (angle - start).rem_euclid(2.0 * PI) < ORIENTATION_WINDOW {
+                sum_x += dx;
+                sum_y += dy;
+            }
+        }
+        let norm = sum_x * sum_x + sum_y * sum_y;
+        if norm > best_norm {
+            best_norm = norm;
+            best_angle = sum_y.atan2(sum_x);
+        }
+    }
+
+    best_angle.rem_euclid(2.0 * PI)
+}
+
+/// 计算描述子
+/// 中心在影像外的特征点会被移除
+fn compute_descriptors(integral_image: &[Vec<f64>], keypoints: &mut Vec<KeyPoint>, extended: bool) -> Vec<SurfDescriptor> {
+    let width = integral_image.len() as f64;
+    let height = integral_image.first().map_or(0, |column| column.len()) as f64;
+    keypoints.retain(|keypoint| {
+        let location = keypoint.location;
+        location.x >= 0.0 && location.y >= 0.0 && location.x < width && location.y < height && keypoint.size > 0.0
+    });
+    keypoints
+        .iter()
+        .map(|keypoint| compute_descriptor(integral_image, keypoint, extended))
+        .collect()
+}
+
+/// 单个特征点的描述子
+/// 旋转到主方向后边长20s的区域划分为4x4个子区域，每个子区域5x5个采样点计算边长2s的Haar小波响应
+/// 64维：每个子区域Σdx, Σdy, Σ|dx|, Σ|dy|
+/// 128维：dx相关的和按dy符号拆分，dy相关的和按dx符号拆分
+fn compute_descriptor(integral_image: &[Vec<f64>], keypoint: &KeyPoint, extended: bool) -> SurfDescriptor {
+    let scale = keypoint_scale(keypoint);
+    let haar_size = 2 * scale.round().max(1.0) as i32;
+    let (sin_angle, cos_angle) = keypoint.angle.sin_cos();
+    let center_x = keypoint.location.x;
+    let center_y = keypoint.location.y;
+    let half_width = (DESCRIPTOR_REGIONS * DESCRIPTOR_SAMPLES) as f64 / 2.0;
+    let values_per_region = if extended { 8 } else { 4 };
+
+    let mut values = Vec::with_capacity(DESCRIPTOR_REGIONS * DESCRIPTOR_REGIONS * values_per_region);
+    for region_y in 0..DESCRIPTOR_REGIONS {
+        for region_x in 0..DESCRIPTOR_REGIONS {
+            let mut sums = [0.0f64; 8];
+            for sample_y in 0..DESCRIPTOR_SAMPLES {
+                for sample_x in 0..DESCRIPTOR_SAMPLES {
+                    // 特征点坐标系下的采样位置，单位为s
+                    let u = (region_x * DESCRIPTOR_SAMPLES + sample_x) as f64 + 0.5 - half_width;
+                    let v = (region_y * DESCRIPTOR_SAMPLES + sample_y) as f64 + 0.5 - half_width;
+                    let x = (center_x + (u * cos_angle - v * sin_angle) * scale).round() as i32;
+                    let y = (center_y + (u * sin_angle + v * cos_angle) * scale).round() as i32;
+                    let weight = (-(u * u + v * v) / (2.0 * DESCRIPTOR_SIGMA * DESCRIPTOR_SIGMA)).exp();
+
+                    let response_x = haar_x(integral_image, x, y, haar_size);
+                    let response_y = haar_y(integral_image, x, y, haar_size);
+                    // 旋转到特征点坐标系
+                    let dx = weight * (response_x * cos_angle + response_y * sin_angle);
+                    let dy = weight * (-response_x * sin_angle + response_y * cos_angle);
+
+                    if extended {
+                        let (i, j) = (if dy >= 0.0 { 0 } else { 2 }, if dx >= 0.0 { 4 } else { 6 });
+                        sums[i] += dx;
+                        sums[i + 1] += dx.abs();
+                        sums[j] += dy;
+                        sums[j + 1] += dy.abs();
+                    } else {
+                        sums[0] += dx;
+                        sums[1] += dy;
+                        sums[2] += dx.abs();
+                        sums[3] += dy.abs();
+                    }
+                }
+            }
+            values.extend(sums[..values_per_region].iter().map(|&value| value as f32));
+        }
     }
 
-    fn match_features(feature1:&Vec<Self>,feature2:&Vec<Self>)->Vec<(usize,usize)> where Self:Sized {
-        todo!()
+    let norm = values.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > f32::EPSILON {
+        for value in values.iter_mut() {
+            *value /= norm;
+        }
     }
+
+    // 特征点尺度下Hessian矩阵迹的符号
+    let lobe = lobe_size(keypoint.size.round() as u32);
+    let x = keypoint.location.x.round() as i32;
+    let y = keypoint.location.y.round() as i32;
+    let laplacian = dxx(integral_image, x, y, lobe) + dyy(integral_image, x, y, lobe) >= 0.0;
+
+    SurfDescriptor { laplacian, values }
+}
+
+/// Haar小波x方向响应：右半边减左半边
+fn haar_x(integral_image: &[Vec<f64>], x: i32, y: i32, size: i32) -> f64 {
+    let half = size / 2;
+    region_sum(integral_image, x, y - half, half, size) - region_sum(integral_image, x - half, y - half, half, size)
+}
+
+/// Haar小波y方向响应：下半边减上半边
+fn haar_y(integral_image: &[Vec<f64>], x: i32, y: i32, size: i32) -> f64 {
+    let half = size / 2;
+    region_sum(integral_image, x - half, y, size, half) - region_sum(integral_image, x - half, y - half, size, half)
 }
 
 /// 计算积分图，与输入图像大小相等
@@ -48,52 +421,156 @@ fn compute_integral_image(image: &image::GrayImage) -> Vec<Vec<f64>> {
     integral_image
 }
 
+/// 滤波器边长对应的二阶导数模板瓣宽
+fn lobe_size(size: u32) -> i32 {
+    (size / 3) as i32
+}
+
 /// 计算给定尺度的盒子滤波器响应
-fn box_filter_response(integral_image: &Vec<Vec<f64>>, x: u32, y: u32, scale: u32) -> f64 {
+/// 即Hessian矩阵行列式的近似，已按滤波器面积归一化
+fn box_filter_response(integral_image: &[Vec<f64>], x: u32, y: u32, size: u32) -> f64 {
     let x = x as i32;
     let y = y as i32;
-    let half_scale = (scale / 2) as i32;
-    let quarter_scale = (scale / 4) as i32;
+    let lobe = lobe_size(size);
+    let inverse_area = 1.0 / (size * size) as f64;
 
-    let dxx = dxx(integral_image, x, y, half_scale);
-    let dyy = dyy(integral_image, x, y, half_scale);
-    let dxy = dxy(integral_image, x, y, quarter_scale);
+    let dxx = dxx(integral_image, x, y, lobe) * inverse_area;
+    let dyy = dyy(integral_image, x, y, lobe) * inverse_area;
+    let dxy = dxy(integral_image, x, y, lobe) * inverse_area;
 
-    dxx * dyy - dxy * dxy
+    dxx * dyy - DXY_WEIGHT * DXY_WEIGHT * dxy * dxy
 }
 
-/// 计算给定区域的像素和
-fn box_filter(integral_image: &Vec<Vec<f64>>, x: i32, y: i32, width: i32, height: i32) -> f64 {
-    let x1 = (x - width / 2).max(0) as usize;// 左
-    let x2 = (x + width / 2).min(integral_image.len() as i32 - 1) as usize;// 右
-    let y1 = (y - height / 2).max(0) as usize;// 上
-    let y2 = (y + height / 2).min(integral_image[0].len() as i32 - 1) as usize;// 下
+/// 计算以(x, y)为中心的区域的像素和
+fn box_filter(integral_image: &[Vec<f64>], x: i32, y: i32, width: i32, height: i32) -> f64 {
+    region_sum(integral_image, x - width / 2, y - height / 2, width, height)
+}
+
+/// 计算左上角为(left, top)的区域的像素和，超出影像的部分记为0
+fn region_sum(integral_image: &[Vec<f64>], left: i32, top: i32, width: i32, height: i32) -> f64 {
+    let image_width = integral_image.len() as i32;
+    let image_height = integral_image.first().map_or(0, |column| column.len()) as i32;
+    let x1 = left.max(0) - 1;// 左边界外一列
+    let x2 = (left + width - 1).min(image_width - 1);// 右
+    let y1 = top.max(0) - 1;// 上边界外一行
+    let y2 = (top + height - 1).min(image_height - 1);// 下
+    if x2 <= x1 || y2 <= y1 {
+        return 0.0;
+    }
 
-    let a = integral_image[x1][y1];// 左上
-    let b = integral_image[x2][y1];// 右上
-    let c = integral_image[x1][y2];// 左下
-    let d = integral_image[x2][y2];// 右下
+    let at = |x: i32, y: i32| if x < 0 || y < 0 { 0.0 } else { integral_image[x as usize][y as usize] };
+    let a = at(x1, y1);// 左上
+    let b = at(x2, y1);// 右上
+    let c = at(x1, y2);// 左下
+    let d = at(x2, y2);// 右下
 
     d - b - c + a
 }
 
 /// 近似高斯二阶微分模板Dxx
-fn dxx(integral_image: &Vec<Vec<f64>>, x: i32, y: i32, scale: i32) -> f64 {
-    box_filter(integral_image, x - scale, y, scale, 2 * scale) -
-    2.0 * box_filter(integral_image, x, y, scale, 2 * scale) +
-    box_filter(integral_image, x + scale, y, scale, 2 * scale)
+/// 水平排列的三个瓣，宽lobe、高2 * lobe - 1，权重1, -2, 1
+fn dxx(integral_image: &[Vec<f64>], x: i32, y: i32, lobe: i32) -> f64 {
+    box_filter(integral_image, x - lobe, y, lobe, 2 * lobe - 1) -
+    2.0 * box_filter(integral_image, x, y, lobe, 2 * lobe - 1) +
+    box_filter(integral_image, x + lobe, y, lobe, 2 * lobe - 1)
 }
 
-fn dyy(integral_image: &Vec<Vec<f64>>, x: i32, y: i32, scale: i32) -> f64 {
-    box_filter(integral_image, x, y - scale, 2 * scale, scale) -
-    2.0 * box_filter(integral_image, x, y, 2 * scale, scale) +
-    box_filter(integral_image, x, y + scale, 2 * scale, scale)
+/// 近似高斯二阶微分模板Dyy，Dxx的转置
+fn dyy(integral_image: &[Vec<f64>], x: i32, y: i32, lobe: i32) -> f64 {
+    box_filter(integral_image, x, y - lobe, 2 * lobe - 1, lobe) -
+    2.0 * box_filter(integral_image, x, y, 2 * lobe - 1, lobe) +
+    box_filter(integral_image, x, y + lobe, 2 * lobe - 1, lobe)
 }
 
+/// 近似高斯二阶微分模板Dxy
+/// 四个lobe x lobe的方块，左上、右下为1，右上、左下为-1
+fn dxy(integral_image: &[Vec<f64>], x: i32, y: i32, lobe: i32) -> f64 {
+    let offset = (lobe + 1) / 2;
+    box_filter(integral_image, x - offset, y - offset, lobe, lobe) +
+    box_filter(integral_image, x + offset, y + offset, lobe, lobe) -
+    box_filter(integral_image, x + offset, y - offset, lobe, lobe) -
+    box_filter(integral_image, x - offset, y + offset, lobe, lobe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::imageops::invert;
+
+    /// 若干大小、亮度不同的椭圆斑点
+    fn blob_image() -> GrayImage {
+        let mut state: u32 = 12345;
+        let mut next = || {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 8) as f64 / (1 << 24) as f64
+        };
+        let blobs: Vec<[f64; 6]> = (0..150)
+            .map(|_| [20.0 + 160.0 * next(), 20.0 + 160.0 * next(), 2.0 + 4.0 * next(), 2.0 + 4.0 * next(), PI * next(), 200.0 * next() - 100.0])
+            .collect();
+        GrayImage::from_fn(200, 200, |x, y| {
+            let value: f64 = blobs
+                .iter()
+                .map(|&[bx, by, sx, sy, angle, amplitude]| {
+                    let (sin_angle, cos_angle) = angle.sin_cos();
+                    let (dx, dy) = (x as f64 - bx, y as f64 - by);
+                    let u = dx * cos_angle + dy * sin_angle;
+                    let v = -dx * sin_angle + dy * cos_angle;
+                    amplitude * (-(u * u) / (2.0 * sx * sx) - (v * v) / (2.0 * sy * sy)).exp()
+                })
+                .sum();
+            image::Luma([(128.0 + value).clamp(0.0, 255.0) as u8])
+        })
+    }
 
-fn dxy(integral_image: &Vec<Vec<f64>>, x: i32, y: i32, scale: i32) -> f64 {
-    box_filter(integral_image, x - scale, y - scale, scale, scale) +
-    box_filter(integral_image, x, y, scale, scale) -
-    box_filter(integral_image, x, y - scale, scale, scale) -
-    box_filter(integral_image, x - scale, y, scale, scale)
+    #[test]
+    fn integral_box_sums_are_exact() {
+        let image = GrayImage::from_fn(7, 5, |x, y| image::Luma([(x + 10 * y) as u8]));
+        let integral_image = compute_integral_image(&image);
+        let expected: f64 = (1..4).flat_map(|x| (2..4).map(move |y| (x + 10 * y) as f64)).sum();
+        assert_eq!(region_sum(&integral_image, 1, 2, 3, 2), expected);
+        assert_eq!(box_filter(&integral_image, 2, 3, 3, 3), (1..4).flat_map(|x| (2..5).map(move |y| (x + 10 * y) as f64)).sum());
+        // 超出影像的部分记为0
+        let clipped = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| -> f64 {
+            xs.flat_map(|x| ys.clone().map(move |y| (x + 10 * y) as f64)).sum()
+        };
+        assert_eq!(region_sum(&integral_image, -2, -2, 4, 4), clipped(0..2, 0..2));
+        assert_eq!(region_sum(&integral_image, 5, 3, 4, 4), clipped(5..7, 3..5));
+        assert_eq!(region_sum(&integral_image, -3, -3, 3, 3), 0.0);
+    }
+
+    #[test]
+    fn laplacian_flips_with_contrast() {
+        for extended in [false, true] {
+            let surf = SURF { extended, ..SURF::default() };
+            let image = blob_image();
+            let (keypoints, descriptors) = surf.extract(&image);
+            assert!(keypoints.len() >= 10, "{}", keypoints.len());
+            let values_per_region = if extended { 8 } else { 4 };
+            assert!(descriptors.iter().all(|descriptor| descriptor.values.len() == 16 * values_per_region));
+
+            // 反色后亮暗斑点互换：位置不变，laplacian取反
+            // 梯度取反、主方向转π，各子区域的Haar响应不变，只是子区域顺序倒转
+            let mut inverted = image.clone();
+            invert(&mut inverted);
+            let (inverted_keypoints, inverted_descriptors) = surf.extract(&inverted);
+            let num_flipped = keypoints
+                .iter()
+                .zip(descriptors.iter())
+                .filter(|(keypoint, descriptor)| {
+                    let Some(index) = inverted_keypoints.iter().position(|other| (other.location - keypoint.location).norm() < 1.0) else {
+                        return false;
+                    };
+                    let other = &inverted_descriptors[index];
+                    let reversed: Vec<f32> = other.values.chunks(values_per_region).rev().flatten().copied().collect();
+                    let difference = SurfDescriptor { laplacian: other.laplacian, values: reversed }.distance(descriptor);
+                    other.laplacian != descriptor.laplacian && difference < 0.2
+                })
+                .count();
+            assert!(num_flipped * 10 >= keypoints.len() * 8, "{} / {}", num_flipped, keypoints.len());
+
+            // laplacian不同的描述子不参与匹配
+            let matches = surf.match_descriptors(&descriptors, &inverted_descriptors);
+            assert!(matches.iter().all(|&(i, j)| descriptors[i].laplacian == inverted_descriptors[j].laplacian));
+        }
+    }
 }
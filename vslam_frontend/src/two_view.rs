@@ -0,0 +1,377 @@
+use crate::ransac::{Estimator, Ransac};
+use nalgebra::{DMatrix, Matrix3, Vector2, Vector3};
+use vslam_core::feature::KeyPoint;
+
+/// 1自由度卡方分布95%分位数，基础矩阵的点到极线距离
+const CHI2_ONE_DOF: f64 = 3.841;
+/// 2自由度卡方分布95%分位数，单应的转移误差
+const CHI2_TWO_DOF: f64 = 5.991;
+/// 单应得分占比超过该值时选择单应
+const HOMOGRAPHY_SCORE_RATIO: f64 = 0.40;
+
+/// 单目初始化的两视图模型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TwoViewModel {
+    Homography(Matrix3<f64>),  // x2 ~ H·x1，平面场景或纯旋转
+    Fundamental(Matrix3<f64>), // x2ᵀ·F·x1 = 0，一般场景
+}
+
+/// 模型估计结果
+#[derive(Clone, Debug)]
+pub struct ModelEstimate {
+    pub model: Matrix3<f64>, // 像素坐标下的单应或基础矩阵
+    pub score: f64,          // 对称转移误差得分，越大越好
+    pub inliers: Vec<bool>,  // 与输入的匹配一一对应
+}
+
+/// 模型选择结果
+#[derive(Clone, Debug)]
+pub struct ModelSelection {
+    pub model: TwoViewModel,
+    pub inliers: Vec<bool>,   // 所选模型的内点
+    pub homography_score: f64,
+    pub fundamental_score: f64,
+}
+
+/// 单应和基础矩阵的RANSAC估计及模型选择，与ORB-SLAM初始化相同
+/// 两个模型使用相同种子的8点采样，迭代次数固定，每个内点的得分为卡方阈值减去归一化的误差平方，两个方向的误差都计入
+#[derive(Clone, Copy, Debug)]
+pub struct TwoViewEstimator {
+    pub sigma: f64,        // 特征点位置的标准差，单位像素
+    pub iterations: usize, // RANSAC迭代次数
+    pub seed: u64,         // 随机采样的种子
+}
+
+impl Default for TwoViewEstimator {
+    fn default() -> Self {
+        TwoViewEstimator { sigma: 1.0, iterations: 200, seed: 0 }
+    }
+}
+
+impl TwoViewEstimator {
+    /// 同时估计单应和基础矩阵，RH = SH / (SH + SF) > 0.40时选择单应
+    /// matches为(第一帧特征序号, 第二帧特征序号)，特征点应已去畸变
+    /// 匹配少于8对时返回None
+    pub fn select_model(&self, keypoints1: &[KeyPoint], keypoints2: &[KeyPoint], matches: &[(usize, usize)]) -> Option<ModelSelection> {
+        let homography = self.estimate_homography(keypoints1, keypoints2, matches);
+        let fundamental = self.estimate_fundamental(keypoints1, keypoints2, matches);
+
+        let homography_score = homography.as_ref().map_or(0.0, |estimate| estimate.score);
+        let fundamental_score = fundamental.as_ref().map_or(0.0, |estimate| estimate.score);
+        if homography_score + fundamental_score <= 0.0 {
+            return None;
+        }
+        let ratio = homography_score / (homography_score + fundamental_score);
+        let (model, inliers) = if ratio > HOMOGRAPHY_SCORE_RATIO {
+            let estimate = homography?;
+            (TwoViewModel::Homography(estimate.model), estimate.inliers)
+        } else {
+            let estimate = fundamental?;
+            (TwoViewModel::Fundamental(estimate.model), estimate.inliers)
+        };
+        Some(ModelSelection { model, inliers, homography_score, fundamental_score })
+    }
+
+    /// 单应的RANSAC估计
+    pub fn estimate_homography(&self, keypoints1: &[KeyPoint], keypoints2: &[KeyPoint], matches: &[(usize, usize)]) -> Option<ModelEstimate> {
+        let (points1, points2) = matched_points(keypoints1, keypoints2, matches);
+        let solver = HomographySolver { points1: &points1, points2: &points2, inv_sigma2: 1.0 / (self.sigma * self.sigma) };
+        let result = self.ransac(CHI2_TWO_DOF).run(&solver)?;
+        Some(ModelEstimate { model: result.model.0, score: result.score, inliers: result.inliers })
+    }
+
+    /// 基础矩阵的RANSAC估计
+    pub fn estimate_fundamental(&self, keypoints1: &[KeyPoint], keypoints2: &[KeyPoint], matches: &[(usize, usize)]) -> Option<ModelEstimate> {
+        let (points1, points2) = matched_points(keypoints1, keypoints2, matches);
+        let solver = FundamentalSolver { points1: &points1, points2: &points2, inv_sigma2: 1.0 / (self.sigma * self.sigma) };
+        let result = self.ransac(CHI2_ONE_DOF).run(&solver)?;
+        Some(ModelEstimate { model: result.model, score: result.score, inliers: result.inliers })
+    }
+
+    /// 固定迭代次数、不做局部优化的RANSAC
+    fn ransac(&self, threshold: f64) -> Ransac {
+        Ransac {
+            threshold,
+            min_iterations: self.iterations,
+            max_iterations: self.iterations,
+            local_optimization: false,
+            seed: self.seed,
+            ..Ransac::default()
+        }
+    }
+}
+
+/// 单应的8点解算器，模型为(H, H⁻¹)，残差为两个方向中较大的归一化转移误差
+struct HomographySolver<'a> {
+    points1: &'a [Vector2<f64>],
+    points2: &'a [Vector2<f64>],
+    inv_sigma2: f64,
+}
+
+impl Estimator for HomographySolver<'_> {
+    type Model = (Matrix3<f64>, Matrix3<f64>);
+
+    fn sample_size(&self) -> usize {
+        8
+    }
+
+    fn num_data(&self) -> usize {
+        self.points1.len()
+    }
+
+    fn fit(&self, sample: &[usize]) -> Vec<Self::Model> {
+        let sample1: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points1[i]).collect();
+        let sample2: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points2[i]).collect();
+        dlt_homography(&sample1, &sample2)
+            .and_then(|homography| Some((homography, homography.try_inverse()?)))
+            .into_iter()
+            .collect()
+    }
+
+    fn residual(&self, model: &Self::Model, index: usize) -> f64 {
+        let (forward, backward) = homography_errors(&model.0, &model.1, &self.points1[index], &self.points2[index], self.inv_sigma2);
+        forward.max(backward)
+    }
+
+    fn score(&self, model: &Self::Model, index: usize, threshold: f64) -> Option<f64> {
+        let (forward, backward) = homography_errors(&model.0, &model.1, &self.points1[index], &self.points2[index], self.inv_sigma2);
+        (forward.max(backward) <= threshold).then_some(2.0 * CHI2_TWO_DOF - forward - backward)
+    }
+}
+
+/// 基础矩阵的8点解算器，残差为两个方向中较大的归一化点到极线距离
+struct FundamentalSolver<'a> {
+    points1: &'a [Vector2<f64>],
+    points2: &'a [Vector2<f64>],
+    inv_sigma2: f64,
+}
+
+impl Estimator for FundamentalSolver<'_> {
+    type Model = Matrix3<f64>;
+
+    fn sample_size(&self) -> usize {
+        8
+    }
+
+    fn num_data(&self) -> usize {
+        self.points1.len()
+    }
+
+    fn fit(&self, sample: &[usize]) -> Vec<Matrix3<f64>> {
+        let sample1: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points1[i]).collect();
+        let sample2: Vec<Vector2<f64>> = sample.iter().map(|&i| self.points2[i]).collect();
+        eight_point(&sample1, &sample2).into_iter().collect()
+    }
+
+    fn residual(&self, fundamental: &Matrix3<f64>, index: usize) -> f64 {
+        let (forward, backward) = fundamental_errors(fundamental, &self.points1[index], &self.points2[index], self.inv_sigma2);
+        forward.max(backward)
+    }
+
+    /// 与单应使用相同的2自由度阈值计分，使两个模型的得分可比
+    fn score(&self, fundamental: &Matrix3<f64>, index: usize, threshold: f64) -> Option<f64> {
+        let (forward, backward) = fundamental_errors(fundamental, &self.points1[index], &self.points2[index], self.inv_sigma2);
+        (forward.max(backward) <= threshold).then_some(2.0 * CHI2_TWO_DOF - forward - backward)
+    }
+}
+
+fn matched_points(keypoints1: &[KeyPoint], keypoints2: &[KeyPoint], matches: &[(usize, usize)]) -> (Vec<Vector2<f64>>, Vec<Vector2<f64>>) {
+    matches
+        .iter()
+        .map(|&(i, j)| (keypoints1[i].location, keypoints2[j].location))
+        .unzip()
+}
+
+/// Hartley归一化：平移到质心，缩放到平均距离为√2
+/// 返回归一化后的点和变换T，归一化点 = T·点
+pub fn normalize_points(points: &[Vector2<f64>]) -> (Vec<Vector2<f64>>, Matrix3<f64>) {
+    let centroid = points.iter().sum::<Vector2<f64>>() / points.len().max(1) as f64;
+    let mean_distance = points.iter().map(|point| (point - centroid).norm()).sum::<f64>() / points.len().max(1) as f64;
+    let scale = if mean_distance > 0.0 { std::f64::consts::SQRT_2 / mean_distance } else { 1.0 };
+    let normalized = points.iter().map(|point| (point - centroid) * scale).collect();
+    let transform = Matrix3::new(scale, 0.0, -scale * centroid.x, 0.0, scale, -scale * centroid.y, 0.0, 0.0, 1.0);
+    (normalized, transform)
+}
+
+/// 齐次线性方程组A·h = 0的最小二乘解，A行数不足时补零
+fn null_vector(rows: &[[f64; 9]]) -> Option<[f64; 9]> {
+    let a = DMatrix::from_fn(rows.len().max(9), 9, |r, c| rows.get(r).map_or(0.0, |row| row[c]));
+    let v_t = a.svd(false, true).v_t?;
+    Some(std::array::from_fn(|c| v_t[(8, c)]))
+}
+
+/// 归一化八点法估计基础矩阵，x2ᵀ·F·x1 = 0，并强制秩为2
+/// 至少需要8对点
+pub fn eight_point(points1: &[Vector2<f64>], points2: &[Vector2<f64>]) -> Option<Matrix3<f64>> {
+    if points1.len() < 8 || points1.len() != points2.len() {
+        return None;
+    }
+    let (normalized1, transform1) = normalize_points(points1);
+    let (normalized2, transform2) = normalize_points(points2);
+    let rows: Vec<[f64; 9]> = normalized1
+        .iter()
+        .zip(normalized2.iter())
+        .map(|(p1, p2)| [p2.x * p1.x, p2.x * p1.y, p2.x, p2.y * p1.x, p2.y * p1.y, p2.y, p1.x, p1.y, 1.0])
+        .collect();
+    let f = null_vector(&rows)?;
+    let fundamental = Matrix3::from_row_slice(&f);
+
+    let mut svd = fundamental.svd(true, true);
+    svd.singular_values[2] = 0.0;
+    let fundamental = transform2.transpose() * svd.recompose().ok()? * transform1;
+    let norm = fundamental.norm();
+    (norm > 0.0).then(|| fundamental / norm)
+}
+
+/// DLT估计单应，x2 ~ H·x1，输入先做Hartley归一化
+/// 至少需要4对点
+pub fn dlt_homography(points1: &[Vector2<f64>], points2: &[Vector2<f64>]) -> Option<Matrix3<f64>> {
+    if points1.len() < 4 || points1.len() != points2.len() {
+        return None;
+    }
+    let (normalized1, transform1) = normalize_points(points1);
+    let (normalized2, transform2) = normalize_points(points2);
+    let rows: Vec<[f64; 9]> = normalized1
+        .iter()
+        .zip(normalized2.iter())
+        .flat_map(|(p1, p2)| {
+            [
+                [0.0, 0.0, 0.0, -p1.x, -p1.y, -1.0, p2.y * p1.x, p2.y * p1.y, p2.y],
+                [p1.x, p1.y, 1.0, 0.0, 0.0, 0.0, -p2.x * p1.x, -p2.x * p1.y, -p2.x],
+            ]
+        })
+        .collect();
+    let h = null_vector(&rows)?;
+    let homography = transform2.try_inverse()? * Matrix3::from_row_slice(&h) * transform1;
+    if homography[(2, 2)].abs() < 1e-12 {
+        return None;
+    }
+    Some(homography / homography[(2, 2)])
+}
+
+/// 单应两个方向的转移误差平方，乘以inv_sigma2归一化
+fn homography_errors(
+    homography: &Matrix3<f64>,
+    inverse: &Matrix3<f64>,
+    point1: &Vector2<f64>,
+    point2: &Vector2<f64>,
+    inv_sigma2: f64,
+) -> (f64, f64) {
+    let transfer = |h: &Matrix3<f64>, from: &Vector2<f64>, to: &Vector2<f64>| {
+        let projected = h * from.push(1.0);
+        if projected.z.abs() < 1e-12 {
+            return f64::INFINITY;
+        }
+        (projected.xy() / projected.z - to).norm_squared() * inv_sigma2
+    };
+    (transfer(homography, point1, point2), transfer(inverse, point2, point1))
+}
+
+/// 基础矩阵两个方向的点到极线距离平方，乘以inv_sigma2归一化
+fn fundamental_errors(fundamental: &Matrix3<f64>, point1: &Vector2<f64>, point2: &Vector2<f64>, inv_sigma2: f64) -> (f64, f64) {
+    let distance = |line: Vector3<f64>, point: &Vector2<f64>| {
+        let norm2 = line.x * line.x + line.y * line.y;
+        if norm2 <= 0.0 {
+            return f64::INFINITY;
+        }
+        line.dot(&point.push(1.0)).powi(2) / norm2 * inv_sigma2
+    };
+    (distance(fundamental * point1.push(1.0), point2), distance(fundamental.transpose() * point2.push(1.0), point1))
+}
+
+/// 单应的对称转移误差得分，两个方向的误差平方除以σ²，超过2自由度卡方阈值为外点
+pub fn check_homography(homography: &Matrix3<f64>, points1: &[Vector2<f64>], points2: &[Vector2<f64>], sigma: f64) -> (f64, Vec<bool>) {
+    let Some(inverse) = homography.try_inverse() else {
+        return (0.0, vec![false; points1.len()]);
+    };
+    let solver = HomographySolver { points1, points2, inv_sigma2: 1.0 / (sigma * sigma) };
+    check(&solver, &(*homography, inverse), CHI2_TWO_DOF)
+}
+
+/// 基础矩阵的对称转移误差得分，点到极线距离的平方除以σ²，超过1自由度卡方阈值为外点
+/// 得分与单应使用相同的2自由度阈值，使两个模型的得分可比
+pub fn check_fundamental(fundamental: &Matrix3<f64>, points1: &[Vector2<f64>], points2: &[Vector2<f64>], sigma: f64) -> (f64, Vec<bool>) {
+    let solver = FundamentalSolver { points1, points2, inv_sigma2: 1.0 / (sigma * sigma) };
+    check(&solver, fundamental, CHI2_ONE_DOF)
+}
+
+/// 模型在全部数据上的得分和内点
+fn check<E: Estimator>(estimator: &E, model: &E::Model, threshold: f64) -> (f64, Vec<bool>) {
+    let mut score = 0.0;
+    let inliers = (0..estimator.num_data())
+        .map(|index| estimator.score(model, index, threshold).inspect(|s| score += s).is_some())
+        .collect();
+    (score, inliers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::split_mix64;
+    use nalgebra::UnitQuaternion;
+    use vslam_core::camera::{Camera, PinholeCamera};
+    use vslam_core::lie::SE3;
+
+    /// 两帧的匹配特征点，plane为真时所有点位于z = 6的平面上，每5个点中1个为外点
+    fn two_views(plane: bool) -> (Vec<KeyPoint>, Vec<KeyPoint>, Vec<(usize, usize)>) {
+        let camera = PinholeCamera::new(450.0, 450.0, 320.0, 240.0, 640, 480);
+        let pose = SE3::new(UnitQuaternion::from_euler_angles(0.02, -0.05, 0.01), Vector3::new(0.8, 0.1, 0.1));
+        let mut state = if plane { 1 } else { 2 };
+        let mut uniform = |low: f64, high: f64| low + (high - low) * (split_mix64(&mut state) % 100000) as f64 / 100000.0;
+        let mut keypoints1 = Vec::new();
+        let mut keypoints2 = Vec::new();
+        for i in 0..200 {
+            let depth = if plane { 6.0 } else { uniform(3.0, 12.0) };
+            let point = Vector3::new(uniform(-2.0, 2.0), uniform(-1.5, 1.5), depth);
+            let noise = Vector2::new(uniform(-0.3, 0.3), uniform(-0.3, 0.3));
+            let mut pixel2 = camera.project(&pose.transform_point(&point)).unwrap() + noise;
+            if i % 5 == 0 {
+                pixel2 += Vector2::new(uniform(-40.0, 40.0), uniform(20.0, 40.0));
+            }
+            keypoints1.push(KeyPoint::new(camera.project(&point).unwrap()));
+            keypoints2.push(KeyPoint::new(pixel2));
+        }
+        let matches = (0..200).map(|i| (i, i)).collect();
+        (keypoints1, keypoints2, matches)
+    }
+
+    #[test]
+    fn minimal_solvers_fit_exact_data() {
+        let homography = Matrix3::new(1.1, 0.05, 12.0, -0.03, 0.95, -7.0, 1e-4, -2e-4, 1.0);
+        let points1: Vec<Vector2<f64>> = (0..4).map(|i| Vector2::new(50.0 + 90.0 * (i % 2) as f64, 40.0 + 70.0 * (i / 2) as f64)).collect();
+        let points2: Vec<Vector2<f64>> = points1.iter().map(|p| (homography * p.push(1.0)).xy() / (homography * p.push(1.0)).z).collect();
+        let estimated = dlt_homography(&points1, &points2).unwrap();
+        assert!((estimated - homography).norm() < 1e-8, "{}", estimated);
+
+        let (keypoints1, keypoints2, _) = two_views(false);
+        // 下标为5的倍数的是外点，取其余点
+        let indices = [1, 2, 3, 4, 6, 7, 8, 9, 11];
+        let points1: Vec<Vector2<f64>> = indices.iter().map(|&i| keypoints1[i].location).collect();
+        let points2: Vec<Vector2<f64>> = indices.iter().map(|&i| keypoints2[i].location).collect();
+        let fundamental = eight_point(&points1, &points2).unwrap();
+        assert!(fundamental.determinant().abs() < 1e-12);
+        let (_, inliers) = check_fundamental(&fundamental, &points1, &points2, 1.0);
+        assert!(inliers.iter().all(|&inlier| inlier));
+    }
+
+    #[test]
+    fn selects_homography_for_planar_scene() {
+        let (keypoints1, keypoints2, matches) = two_views(true);
+        let selection = TwoViewEstimator::default().select_model(&keypoints1, &keypoints2, &matches).unwrap();
+        assert!(matches!(selection.model, TwoViewModel::Homography(_)), "{:?}", selection);
+        for (i, &inlier) in selection.inliers.iter().enumerate() {
+            assert_eq!(inlier, i % 5 != 0, "{}", i);
+        }
+    }
+
+    #[test]
+    fn selects_fundamental_for_general_scene() {
+        let (keypoints1, keypoints2, matches) = two_views(false);
+        let selection = TwoViewEstimator::default().select_model(&keypoints1, &keypoints2, &matches).unwrap();
+        assert!(matches!(selection.model, TwoViewModel::Fundamental(_)), "{:?}", selection);
+        assert!(selection.homography_score < selection.fundamental_score);
+        let inliers = selection.inliers.iter().filter(|&&inlier| inlier).count();
+        assert!(inliers >= 150, "{}", inliers);
+        assert!(selection.inliers.iter().step_by(5).filter(|&&inlier| inlier).count() <= 2);
+    }
+}